@@ -0,0 +1,167 @@
+//! Benchmark for parsing a large synthetic fest file
+//!
+//! Compare the sequential and the parallel parsing with:
+//!
+//! ```text
+//! cargo bench
+//! cargo bench --features parallel
+//! ```
+//!
+//! The memory of the indexes is measured by counting the allocations
+//! when the indexes of a fest from a snapshot are built on the first
+//! lookup.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::Write;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use festlib::Fest;
+
+// counts the bytes that are allocated and not freed
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const PACKAGES: usize = 50_000;
+const INTERACTIONS: usize = 10_000;
+const SUBSTANCES: usize = 10_000;
+const RUNS: u32 = 5;
+
+fn package(xml: &mut String, i: usize) {
+    write!(xml, r#"
+        <OppfLegemiddelpakning>
+            <Id>ID_OPPF-{i}</Id>
+            <Tidspunkt>2024-04-21T00:51:31</Tidspunkt>
+            <Status V="A" DN="Aktiv oppføring"/>
+            <Legemiddelpakning>
+                <Atc V="A01AA{atc:02}" S="2.16.578.1.12.4.1.1.7180" DN="Test ATC Code"/>
+                <NavnFormStyrke>Test Medicine {i} 10mg Kapsel</NavnFormStyrke>
+                <Reseptgruppe V="C" DN="Reseptgruppe C"/>
+                <Id>ID_PACKAGE-{i}</Id>
+                <Varenr>{i:06}</Varenr>
+                <Ean>70{i:011}</Ean>
+                <Pakningsinfo>
+                    <EnhetPakning V="stk" S="2.16.578.1.12.4.1.1.7452" DN="stk"/>
+                    <Mengde V="28" U="stk"/>
+                    <DDD V="20" U="mg"/>
+                </Pakningsinfo>
+                <PakningByttegruppe>
+                    <RefByttegruppe>BYTTE{group}</RefByttegruppe>
+                </PakningByttegruppe>
+            </Legemiddelpakning>
+        </OppfLegemiddelpakning>"#, atc = i % 100, group = i % 1000).unwrap();
+}
+
+fn interaction(xml: &mut String, i: usize) {
+    write!(xml, r#"
+        <OppfInteraksjon>
+            <Id>ID_OPPF-INT-{i}</Id>
+            <Tidspunkt>2024-04-21T01:00:00</Tidspunkt>
+            <Status V="A" DN="Aktiv oppføring"/>
+            <Interaksjon>
+                <Id>ID_INT-{i}</Id>
+                <Relevans V="1" DN="Bør unngås"/>
+                <KliniskKonsekvens>Økt risiko for blødning</KliniskKonsekvens>
+                <Kildegrunnlag V="1" DN="Interaksjonsstudier"/>
+                <Substansgruppe>
+                    <Substans>
+                        <Substans>Substance A{i}</Substans>
+                        <Atc V="A01AA{a:02}" S="2.16.578.1.12.4.1.1.7180" DN="Test ATC Code"/>
+                    </Substans>
+                </Substansgruppe>
+                <Substansgruppe>
+                    <Substans>
+                        <Substans>Substance B{i}</Substans>
+                        <Atc V="A01AA{b:02}" S="2.16.578.1.12.4.1.1.7180" DN="Test ATC Code"/>
+                    </Substans>
+                </Substansgruppe>
+            </Interaksjon>
+        </OppfInteraksjon>"#, a = i % 100, b = (i + 1) % 100).unwrap();
+}
+
+fn substance(xml: &mut String, i: usize) {
+    write!(xml, r#"
+        <OppfVirkestoff>
+            <Id>ID_OPPF-VIRK-{i}</Id>
+            <Tidspunkt>2024-04-21T01:10:00</Tidspunkt>
+            <Status V="A" DN="Aktiv oppføring"/>
+            <VirkestoffMedStyrke>
+                <Id>ID_STRENGTH-{i}</Id>
+                <Styrke V="5" U="mg"/>
+                <StyrkeNevner V="1" U="ml"/>
+                <RefVirkestoff>ID_SUBST-{i}</RefVirkestoff>
+            </VirkestoffMedStyrke>
+        </OppfVirkestoff>"#).unwrap();
+}
+
+fn synthetic_fest() -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<FEST>\n    <HentetDato>2024-09-09T14:21:28</HentetDato>\n    <KatLegemiddelpakning>");
+    (0..PACKAGES).for_each(|i| package(&mut xml, i));
+    xml.push_str("\n    </KatLegemiddelpakning>\n    <KatInteraksjon>");
+    (0..INTERACTIONS).for_each(|i| interaction(&mut xml, i));
+    xml.push_str("\n    </KatInteraksjon>\n    <KatVirkestoff>");
+    (0..SUBSTANCES).for_each(|i| substance(&mut xml, i));
+    xml.push_str("\n    </KatVirkestoff>\n</FEST>\n");
+    xml
+}
+
+fn main() {
+    let content = synthetic_fest();
+    let parallel = cfg!(feature = "parallel");
+
+    println!("fest file: {} MB, parallel: {}", content.len() / 1_000_000, parallel);
+
+    let mut total = Duration::ZERO;
+    let mut fest = None;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        let parsed = Fest::from_str(&content).expect("Could not parse the synthetic fest file");
+        total += start.elapsed();
+
+        assert_eq!(parsed.packages().len(), PACKAGES);
+        assert_eq!(parsed.substance_strengths().len(), SUBSTANCES);
+        fest = Some(parsed);
+    }
+
+    println!("parse: {:?} per run ({} runs)", total / RUNS, RUNS);
+
+    // the short prefixes match all the packages, the worst case for
+    // suggestions while typing
+    let fest = fest.unwrap();
+    for prefix in ["t", "test medicine 1", "test medicine 4999", "a01aa"] {
+        let start = Instant::now();
+        let completions = fest.complete(prefix, 10).unwrap().count();
+        println!("complete {:?}: {:?} ({} completions)", prefix, start.elapsed(), completions);
+    }
+
+    // a fest from a snapshot builds the indexes on the first lookup
+    let path = std::env::temp_dir().join("festlib_bench.snapshot");
+    fest.save_snapshot(&path).expect("Could not save the snapshot");
+    let restored = Fest::load_snapshot(&path).expect("Could not load the snapshot");
+    std::fs::remove_file(&path).ok();
+
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    restored.find_package("000001").unwrap();
+    let index = ALLOCATED.load(Ordering::Relaxed) - before;
+    println!(
+        "indexes: {:.1} MB, {} bytes per package",
+        index as f64 / 1_000_000.0,
+        index / PACKAGES,
+    );
+}
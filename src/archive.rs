@@ -0,0 +1,60 @@
+use std::io::{Read, Seek};
+use zip::result::ZipError;
+use zip::ZipArchive;
+use crate::error::FestError;
+
+/// The first bytes of a zip file (local file header)
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// Checks if the content is a zip archive
+pub(crate) fn is_zip(content: &[u8]) -> bool {
+    content.starts_with(ZIP_MAGIC)
+}
+
+/// Reads the fest xml file from a zip archive (fest251_inst.zip)
+/// into memory. If the archive has more than one xml file, the
+/// one with fest in the name is used.
+pub(crate) fn read_xml<R: Read + Seek>(reader: R) -> Result<String, FestError> {
+    let mut archive = ZipArchive::new(reader)?;
+
+    let name = archive
+        .file_names()
+        .filter(|n| n.to_lowercase().ends_with(".xml"))
+        .min_by_key(|n| !n.to_lowercase().contains("fest"))
+        .map(String::from)
+        .ok_or(ZipError::FileNotFound)?;
+
+    let mut content = Vec::new();
+    archive.by_name(&name)?.read_to_end(&mut content)?;
+
+    Ok(String::from_utf8(content)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_is_zip() {
+        assert!(is_zip(&fs::read("test_fest.zip").unwrap()));
+        assert!(!is_zip(&fs::read("test_fest.xml").unwrap()));
+    }
+
+    #[test]
+    fn test_read_xml() {
+        let content = read_xml(fs::File::open("test_fest.zip").unwrap()).unwrap();
+        assert_eq!(content, fs::read_to_string("test_fest.xml").unwrap());
+    }
+
+    #[test]
+    fn test_read_xml_no_xml() {
+        let mut buffer = Cursor::new(Vec::new());
+        let mut writer = zip::ZipWriter::new(&mut buffer);
+        writer.start_file("lesmeg.txt", zip::write::SimpleFileOptions::default()).unwrap();
+        writer.finish().unwrap();
+
+        assert!(matches!(read_xml(buffer), Err(FestError::Archive(ZipError::FileNotFound))));
+    }
+}
@@ -0,0 +1,234 @@
+//! The ATC hierarchy from the codes in the packages and substances
+//!
+//! An ATC code has five levels, e.g. N02BE01: N (anatomical main
+//! group), N02 (therapeutic subgroup), N02B (pharmacological subgroup),
+//! N02BE (chemical subgroup) and N02BE01 (chemical substance).
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::kith::Cv;
+use crate::types::{Package, SubstanceStrength};
+
+// the length of the code at each level
+const LEVELS: [usize; 5] = [1, 3, 4, 5, 7];
+
+/// Returns the level (1 to 5) of the ATC code, or None if the code
+/// doesn't have the length of a level
+///
+/// # Example
+/// ```
+/// use festlib::atc_level;
+///
+/// assert_eq!(atc_level("N"), Some(1));
+/// assert_eq!(atc_level("N02BE"), Some(4));
+/// assert_eq!(atc_level("N02BE01"), Some(5));
+/// assert_eq!(atc_level("N02BE0"), None);
+/// ```
+pub fn atc_level(code: &str) -> Option<u8> {
+    LEVELS.iter().position(|l| *l == code.len()).map(|l| l as u8 + 1)
+}
+
+/// A group in the ATC hierarchy, with the number of packages and
+/// substances in the group and the groups below it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AtcNode {
+    code: String,
+    name: Option<String>,
+    packages: usize,
+    substances: usize,
+    children: Vec<AtcNode>,
+}
+
+impl AtcNode {
+    /// Returns the ATC code of the group
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Returns the name of the group (DN), if the fest file has an ATC
+    /// code for exactly this group
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the level, from 1 (anatomical main group) to 5
+    /// (chemical substance)
+    pub fn level(&self) -> u8 {
+        atc_level(&self.code).unwrap_or(0)
+    }
+
+    /// Returns the number of packages in the group
+    pub fn package_count(&self) -> usize {
+        self.packages
+    }
+
+    /// Returns the number of substances with strength in the group
+    /// (OppfVirkestoff)
+    pub fn substance_count(&self) -> usize {
+        self.substances
+    }
+
+    /// Returns the groups on the level below, sorted by code
+    pub fn children(&self) -> &[AtcNode] {
+        &self.children
+    }
+
+    /// Iterates over this group and all the groups below it, depth first
+    pub fn iter(&self) -> impl Iterator<Item = &AtcNode> {
+        let mut stack = vec![self];
+
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children.iter().rev());
+            Some(node)
+        })
+    }
+}
+
+/// The ATC hierarchy from [`Fest::atc_tree`]
+///
+/// # Example
+/// ```
+/// use festlib::Fest;
+/// let fest = Fest::new("test_fest.xml").unwrap();
+///
+/// let tree = fest.atc_tree();
+/// let group = tree.find("A01AA").unwrap();
+///
+/// assert_eq!(group.package_count(), 4);
+/// assert_eq!(group.children()[0].name(), Some("Test ATC Code"));
+/// ```
+///
+/// [`Fest::atc_tree`]: crate::Fest::atc_tree
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AtcTree {
+    roots: Vec<AtcNode>,
+}
+
+impl AtcTree {
+    pub(crate) fn new(packages: &[Package], substance_strengths: &[SubstanceStrength]) -> Self {
+        let mut groups: BTreeMap<String, (Option<String>, usize, usize)> = BTreeMap::new();
+
+        let mut add = |atc: Option<&Cv>, package: bool| {
+            let Some(code) = atc.and_then(Cv::v).map(str::trim) else {
+                return;
+            };
+
+            for prefix in LEVELS.iter().filter_map(|l| code.get(..*l)) {
+                let group = groups.entry(prefix.to_string()).or_default();
+                match package {
+                    true => group.1 += 1,
+                    false => group.2 += 1,
+                }
+            }
+
+            // codes that aren't on a level are counted in the levels above
+            if atc_level(code).is_some() {
+                let group = groups.entry(code.to_string()).or_default();
+                if group.0.is_none() {
+                    group.0 = atc.and_then(Cv::dn).map(str::to_string);
+                }
+            }
+        };
+
+        for package in packages {
+            add(package.atc(), true);
+        }
+        for strength in substance_strengths {
+            add(strength.atc(), false);
+        }
+
+        AtcTree {
+            roots: children(&groups, ""),
+        }
+    }
+
+    /// Returns the anatomical main groups (level 1), sorted by code
+    pub fn roots(&self) -> &[AtcNode] {
+        &self.roots
+    }
+
+    /// Returns the group with the code
+    pub fn find(&self, code: &str) -> Option<&AtcNode> {
+        let code = code.trim();
+        self.iter().find(|n| n.code.eq_ignore_ascii_case(code))
+    }
+
+    /// Iterates over all the groups, depth first
+    pub fn iter(&self) -> impl Iterator<Item = &AtcNode> {
+        self.roots.iter().flat_map(|r| r.iter())
+    }
+
+    /// Returns the groups on the level (1 to 5), sorted by code
+    pub fn level(&self, level: u8) -> impl Iterator<Item = &AtcNode> {
+        self.iter().filter(move |n| n.level() == level)
+    }
+}
+
+// the groups on the level below the parent
+fn children(groups: &BTreeMap<String, (Option<String>, usize, usize)>, parent: &str) -> Vec<AtcNode> {
+    let Some(length) = LEVELS.iter().find(|l| **l > parent.len()) else {
+        return Vec::new();
+    };
+
+    groups
+        .range(parent.to_string()..)
+        .take_while(|(code, _)| code.starts_with(parent))
+        .filter(|(code, _)| code.len() == *length)
+        .map(|(code, (name, packages, substances))| AtcNode {
+            code: code.clone(),
+            name: name.clone(),
+            packages: *packages,
+            substances: *substances,
+            children: children(groups, code),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xml;
+
+    fn tree() -> AtcTree {
+        let (packages, _, _, strengths) = xml::test_catalogs();
+        AtcTree::new(&packages, &strengths)
+    }
+
+    #[test]
+    fn test_tree() {
+        let tree = tree();
+
+        let roots: Vec<&str> = tree.roots().iter().map(|r| r.code()).collect();
+        assert_eq!(roots, vec!["A", "B"]);
+
+        let a = &tree.roots()[0];
+        assert_eq!((a.level(), a.package_count(), a.substance_count()), (1, 4, 0));
+        assert_eq!(a.name(), None);
+
+        let codes: Vec<&str> = a.iter().map(|n| n.code()).collect();
+        assert_eq!(codes, vec!["A", "A01", "A01A", "A01AA", "A01AA01", "A01AA02"]);
+
+        let b = tree.find("b01aa03").unwrap();
+        assert_eq!((b.level(), b.package_count(), b.substance_count()), (5, 1, 1));
+        assert_eq!(b.name(), Some("Test ATC Code 3"));
+        assert!(b.children().is_empty());
+    }
+
+    #[test]
+    fn test_level() {
+        let tree = tree();
+
+        let substances: Vec<(&str, usize)> = tree.level(5).map(|n| (n.code(), n.package_count())).collect();
+        assert_eq!(substances, vec![("A01AA01", 3), ("A01AA02", 1), ("B01AA03", 1)]);
+        assert_eq!(tree.level(2).count(), 2);
+        assert!(tree.find("C").is_none());
+    }
+
+    #[test]
+    fn test_atc_level() {
+        assert_eq!(atc_level("A01"), Some(2));
+        assert_eq!(atc_level("A01A"), Some(3));
+        assert_eq!(atc_level(""), None);
+    }
+}
@@ -0,0 +1,129 @@
+use std::path::Path;
+use crate::catalog::Catalog;
+use crate::error::FestError;
+use crate::fest::{Fest, LoadOptions};
+
+/// Builder for loading only some of the catalogs in the fest file
+///
+/// Catalogs that are not chosen are not parsed, which makes loading
+/// faster when a service only needs e.g. the packages. The finders
+/// that need a catalog that isn't loaded fail with
+/// `FestError::CatalogNotLoaded`, and [`Fest::require`] checks a
+/// catalog up front.
+///
+/// # Example
+/// ```
+/// use festlib::{Catalog, FestBuilder, FestError};
+///
+/// let fest = FestBuilder::new()
+///     .catalog(Catalog::Packages)
+///     .load("test_fest.xml")
+///     .unwrap();
+///
+/// assert_eq!(fest.packages().len(), 5);
+/// assert!(matches!(
+///     fest.require(Catalog::Interactions),
+///     Err(FestError::CatalogNotLoaded(Catalog::Interactions))
+/// ));
+/// assert!(fest.find_interaction(&[]).is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct FestBuilder {
+    options: LoadOptions,
+}
+
+impl FestBuilder {
+    /// Constructor for a builder without any catalogs
+    pub fn new() -> Self {
+        FestBuilder {
+            options: LoadOptions::new().catalogs(&[]),
+        }
+    }
+
+    /// Parse the catalog
+    pub fn catalog(mut self, catalog: Catalog) -> Self {
+        self.options = self.options.catalog(catalog);
+        self
+    }
+
+    /// Parse all the catalogs festlib can read
+    pub fn all_catalogs(mut self) -> Self {
+        self.options = self.options.catalogs(&Catalog::ALL);
+        self
+    }
+
+    /// Fail with FestError::Warnings if the parse report has warnings
+    pub fn warnings_as_errors(mut self, value: bool) -> Self {
+        self.options = self.options.warnings_as_errors(value);
+        self
+    }
+
+    /// Loads the fest xml file, or the zip archive from DMP
+    pub fn load<P: AsRef<Path>>(&self, filename: P) -> Result<Fest, FestError> {
+        Fest::with_options(filename, &self.options)
+    }
+
+    /// Loads the fest xml file, or the zip archive, from memory
+    pub fn load_bytes(&self, content: &[u8]) -> Result<Fest, FestError> {
+        Fest::from_bytes_with_options(content, &self.options)
+    }
+}
+
+impl Default for FestBuilder {
+    fn default() -> Self {
+        FestBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selected_catalogs() {
+        let fest = FestBuilder::new().catalog(Catalog::Interactions).load("test_fest.xml").unwrap();
+
+        assert!(fest.packages().is_empty());
+        assert!(fest.substance_strengths().is_empty());
+        assert!(fest.require(Catalog::Interactions).is_ok());
+        assert!(matches!(fest.require(Catalog::Packages), Err(FestError::CatalogNotLoaded(Catalog::Packages))));
+        assert!(matches!(fest.find_package("061561"), Err(FestError::CatalogNotLoaded(Catalog::Packages))));
+        assert!(fest.find_interaction(&[]).is_ok());
+    }
+
+    #[test]
+    fn test_finder_needs_catalog() {
+        let fest = FestBuilder::new().catalog(Catalog::Packages).load("test_fest.xml").unwrap();
+        let package = fest.find_package("061561").unwrap().unwrap();
+
+        // no interactions is not the same as an unloaded catalog
+        assert!(matches!(
+            fest.find_interaction(&[package]),
+            Err(FestError::CatalogNotLoaded(Catalog::Interactions))
+        ));
+        assert!(matches!(fest.complete("test", 5), Err(FestError::CatalogNotLoaded(Catalog::SubstanceStrengths))));
+        assert_eq!(fest.find_generic(package).unwrap().count(), 4);
+    }
+
+    #[test]
+    fn test_all_catalogs() {
+        let fest = FestBuilder::new().all_catalogs().load("test_fest.xml").unwrap();
+
+        for catalog in Catalog::ALL {
+            assert!(fest.require(catalog).is_ok());
+        }
+        assert_eq!(fest.substance_strengths().len(), 2);
+    }
+
+    #[test]
+    fn test_missing_catalog() {
+        let content = b"<FEST><KatLegemiddelpakning/></FEST>";
+
+        // only the chosen catalogs must be in the file
+        let fest = FestBuilder::new().catalog(Catalog::Packages).load_bytes(content).unwrap();
+        assert!(fest.packages().is_empty());
+
+        let result = FestBuilder::new().all_catalogs().load_bytes(content);
+        assert!(matches!(result, Err(FestError::MissingCatalog(c)) if c == "KatInteraksjon"));
+    }
+}
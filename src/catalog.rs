@@ -0,0 +1,53 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+
+/// The catalogs (Kat*) in the fest file that festlib can parse
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Catalog {
+    /// Drug packages (KatLegemiddelpakning)
+    Packages,
+    /// Interactions (KatInteraksjon)
+    Interactions,
+    /// Substances with strength (KatVirkestoff)
+    SubstanceStrengths,
+}
+
+impl Catalog {
+    /// All the catalogs festlib can parse
+    pub const ALL: [Catalog; 3] = [Catalog::Packages, Catalog::Interactions, Catalog::SubstanceStrengths];
+
+    /// Returns the name of the catalog element, e.g. KatLegemiddelpakning
+    pub fn name(&self) -> &'static str {
+        match self {
+            Catalog::Packages => "KatLegemiddelpakning",
+            Catalog::Interactions => "KatInteraksjon",
+            Catalog::SubstanceStrengths => "KatVirkestoff",
+        }
+    }
+
+    /// Returns the name of the entries in the catalog, e.g.
+    /// OppfLegemiddelpakning
+    pub fn entry(&self) -> &'static str {
+        match self {
+            Catalog::Packages => "OppfLegemiddelpakning",
+            Catalog::Interactions => "OppfInteraksjon",
+            Catalog::SubstanceStrengths => "OppfVirkestoff",
+        }
+    }
+
+    /// Returns true if the catalog must be in the fest file when it
+    /// is loaded
+    pub(crate) fn is_required(&self) -> bool {
+        matches!(self, Catalog::Packages | Catalog::Interactions)
+    }
+
+    pub(crate) fn all() -> Vec<Catalog> {
+        Catalog::ALL.to_vec()
+    }
+}
+
+impl fmt::Display for Catalog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
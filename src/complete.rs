@@ -0,0 +1,253 @@
+//! Prefix completion of package names, substance names and ATC groups
+//!
+//! The keys are normalized the same way as in the search, and kept in a
+//! sorted list, so the completions for a prefix are a range found with
+//! a binary search.
+
+use std::collections::{BTreeSet, HashMap};
+use serde::{Deserialize, Serialize};
+use crate::search;
+use crate::types::{ActiveSubstance, Package, SubstanceStrength};
+
+/// What a completion is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum CompletionKind {
+    /// Name of a package (NavnFormStyrke), which starts with the brand name
+    Brand,
+    /// Name of a substance
+    Substance,
+    /// ATC group, completed by the name or the code
+    Atc,
+}
+
+/// Suggestion from [`Fest::complete`]
+///
+/// [`Fest::complete`]: crate::Fest::complete
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    kind: CompletionKind,
+    text: String,
+    code: Option<String>,
+}
+
+impl Completion {
+    /// Returns the kind of completion
+    pub fn kind(&self) -> CompletionKind {
+        self.kind
+    }
+
+    /// Returns the text as it is written in the fest file
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the ATC code for an ATC group
+    pub fn code(&self) -> Option<&str> {
+        self.code.as_deref()
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Completions {
+    // normalized key and the position in completions, sorted by key
+    keys: Vec<(String, usize)>,
+    completions: Vec<Completion>,
+}
+
+impl Completions {
+    pub(crate) fn new(
+        packages: &[Package],
+        substances: &[ActiveSubstance],
+        substance_strengths: &[SubstanceStrength],
+    ) -> Self {
+        let mut builder = Builder::default();
+
+        for package in packages {
+            builder.add(CompletionKind::Brand, package.name(), None);
+            if let Some(atc) = package.atc() {
+                builder.atc(atc.v(), atc.dn());
+            }
+        }
+
+        for substance in substances {
+            builder.add(CompletionKind::Substance, substance.name(), None);
+        }
+
+        // the substance with strength only has a reference to the
+        // substance (RefVirkestoff), not the name
+        for strength in substance_strengths {
+            if let Some(atc) = strength.atc() {
+                builder.atc(atc.v(), atc.dn());
+            }
+        }
+
+        builder.completions.ranked()
+    }
+
+    // sorts the completions by rank, so the best completions in a range
+    // of keys are the ones with the lowest positions
+    fn ranked(mut self) -> Self {
+        let mut order: Vec<usize> = (0..self.completions.len()).collect();
+        order.sort_by(|a, b| {
+            let (a, b) = (&self.completions[*a], &self.completions[*b]);
+
+            a.text.len().cmp(&b.text.len())
+                .then_with(|| a.kind.cmp(&b.kind))
+                .then_with(|| a.text.cmp(&b.text))
+        });
+
+        let mut position = vec![0; order.len()];
+        for (rank, i) in order.iter().enumerate() {
+            position[*i] = rank;
+        }
+
+        let mut completions: Vec<Option<Completion>> = self.completions.into_iter().map(Some).collect();
+        self.completions = order.iter().filter_map(|i| completions[*i].take()).collect();
+
+        for (_, i) in self.keys.iter_mut() {
+            *i = position[*i];
+        }
+        self.keys.sort_unstable();
+
+        self
+    }
+
+    /// The best completions for the prefix: the ones where the whole
+    /// text is typed first, then the shortest
+    pub(crate) fn complete(&self, prefix: &str, limit: usize) -> Vec<&Completion> {
+        let prefix = key(prefix);
+        if prefix.is_empty() || limit == 0 {
+            return Vec::new();
+        }
+
+        let start = self.keys.partition_point(|(k, _)| k.as_str() < prefix.as_str());
+        let keys = self.keys[start..].iter().take_while(|(k, _)| k.starts_with(&prefix));
+
+        // the keys that are typed in full are first in the range. A
+        // completion can have several keys, e.g. the name and the code
+        // of an ATC group, and the set removes the duplicates.
+        let mut exact = BTreeSet::new();
+        let mut best = BTreeSet::new();
+        for (k, i) in keys {
+            if *k == prefix {
+                exact.insert(*i);
+            } else if !exact.contains(i) {
+                best.insert(*i);
+                if best.len() > limit {
+                    best.pop_last();
+                }
+            }
+        }
+
+        exact.into_iter()
+            .chain(best)
+            .take(limit)
+            .map(|i| &self.completions[i])
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct Builder {
+    completions: Completions,
+    // the same name is in many packages and interactions
+    seen: HashMap<(CompletionKind, String), usize>,
+}
+
+impl Builder {
+    fn add(&mut self, kind: CompletionKind, text: &str, code: Option<&str>) -> Option<usize> {
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+
+        if let Some(i) = self.seen.get(&(kind, text.to_string())) {
+            return Some(*i);
+        }
+
+        let i = self.completions.completions.len();
+        self.completions.completions.push(Completion {
+            kind,
+            text: text.to_string(),
+            code: code.map(str::to_string),
+        });
+        self.completions.keys.push((key(text), i));
+        self.seen.insert((kind, text.to_string()), i);
+
+        Some(i)
+    }
+
+    fn atc(&mut self, code: Option<&str>, name: Option<&str>) {
+        let (Some(code), Some(name)) = (code, name) else {
+            return;
+        };
+
+        let is_new = !self.seen.contains_key(&(CompletionKind::Atc, name.trim().to_string()));
+        if let Some(i) = self.add(CompletionKind::Atc, name, Some(code)) {
+            if is_new {
+                self.completions.keys.push((key(code), i));
+            }
+        }
+    }
+}
+
+fn key(text: &str) -> String {
+    search::words(text).join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completions() -> Completions {
+        let (packages, _, substances, strengths) = crate::xml::test_catalogs();
+        Completions::new(&packages, &substances, &strengths)
+    }
+
+    fn texts(completions: &[&Completion]) -> Vec<String> {
+        completions.iter().map(|c| c.text().to_string()).collect()
+    }
+
+    #[test]
+    fn test_complete_names() {
+        let completions = completions();
+
+        let result = completions.complete("test med", 10);
+        assert_eq!(
+            texts(&result),
+            vec!["Test Medicine 10mg Kapsel", "Test Medicine 20mg Tablet", "Test Medicine 5mg Solution"]
+        );
+        assert!(result.iter().all(|c| c.kind() == CompletionKind::Brand));
+
+        assert_eq!(completions.complete("TEST MEDICINE 10", 10).len(), 1);
+        assert_eq!(completions.complete("test med", 1).len(), 1);
+        assert!(completions.complete("", 10).is_empty());
+        assert!(completions.complete("xyz", 10).is_empty());
+        assert!(completions.complete("id_", 10).is_empty());
+    }
+
+    #[test]
+    fn test_kinds() {
+        let completions = completions();
+
+        // C has no interactions
+        let result = completions.complete("test s", 10);
+        assert_eq!(texts(&result), vec!["Test Substance A", "Test Substance C"]);
+        assert!(result.iter().all(|c| c.kind() == CompletionKind::Substance));
+
+        // the exact name first, and each ATC group once
+        let result = completions.complete("test atc code", 10);
+        assert_eq!(texts(&result), vec!["Test ATC Code", "Test ATC Code 2", "Test ATC Code 3"]);
+        assert_eq!(result[0].kind(), CompletionKind::Atc);
+        assert_eq!(result[0].code(), Some("A01AA01"));
+    }
+
+    #[test]
+    fn test_atc_code() {
+        let completions = completions();
+
+        let result = completions.complete("a01aa", 10);
+        assert_eq!(result.len(), 2);
+        assert!(result.iter().all(|c| c.code().unwrap().starts_with("A01AA")));
+    }
+}
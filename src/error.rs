@@ -0,0 +1,141 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::string::FromUtf8Error;
+use crate::catalog::Catalog;
+use crate::report::ParseReport;
+
+/// Errors when reading the fest xml file
+#[derive(Debug)]
+pub enum FestError {
+    /// The file could not be read
+    Io(io::Error),
+    /// The file is not valid xml. Line and column are where the
+    /// error was found, starting at 1.
+    Xml {
+        line: u32,
+        column: u32,
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// The file is not valid UTF-8
+    Encoding(FromUtf8Error),
+    /// The zip archive could not be read, or it has no xml file
+    Archive(zip::result::ZipError),
+    /// A catalog (Kat*) that is required is missing from the file
+    MissingCatalog(String),
+    /// The file is not a fest message that festlib can read
+    UnsupportedSchema(String),
+    /// The file has warnings, and warnings are treated as errors
+    Warnings(ParseReport),
+    /// The snapshot is invalid, or made by another version of festlib
+    Snapshot(String),
+    /// The query needs a catalog that wasn't loaded
+    CatalogNotLoaded(Catalog),
+    /// The textual query could not be parsed
+    Query(String),
+}
+
+impl FestError {
+    pub(crate) fn xml<E>(line: u32, column: u32, source: E) -> Self
+    where
+        E: Into<Box<dyn Error + Send + Sync>>,
+    {
+        FestError::Xml {
+            line,
+            column,
+            source: source.into(),
+        }
+    }
+}
+
+impl fmt::Display for FestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FestError::Io(_) => write!(f, "Could not read the fest file"),
+            FestError::Xml { line, column, .. } => {
+                write!(f, "Invalid xml at line {}, column {}", line, column)
+            }
+            FestError::Encoding(_) => write!(f, "The fest file is not valid UTF-8"),
+            FestError::Archive(_) => write!(f, "Could not read the fest xml from the zip archive"),
+            FestError::MissingCatalog(catalog) => write!(f, "Missing catalog <{}>", catalog),
+            FestError::UnsupportedSchema(schema) => write!(f, "Unsupported fest schema: {}", schema),
+            FestError::Warnings(report) => match report.warnings().first() {
+                Some(w) => write!(f, "{} warnings in the fest file, first: {}", report.len(), w),
+                None => write!(f, "Warnings in the fest file"),
+            },
+            FestError::Snapshot(reason) => write!(f, "Invalid snapshot: {}", reason),
+            FestError::CatalogNotLoaded(catalog) => write!(f, "The catalog <{}> is not loaded", catalog),
+            FestError::Query(reason) => write!(f, "Invalid query: {}", reason),
+        }
+    }
+}
+
+impl Error for FestError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FestError::Io(e) => Some(e),
+            FestError::Xml { source, .. } => Some(source.as_ref()),
+            FestError::Encoding(e) => Some(e),
+            FestError::Archive(e) => Some(e),
+            FestError::MissingCatalog(_)
+            | FestError::UnsupportedSchema(_)
+            | FestError::Warnings(_)
+            | FestError::Snapshot(_)
+            | FestError::CatalogNotLoaded(_)
+            | FestError::Query(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for FestError {
+    fn from(error: io::Error) -> Self {
+        FestError::Io(error)
+    }
+}
+
+impl From<FromUtf8Error> for FestError {
+    fn from(error: FromUtf8Error) -> Self {
+        FestError::Encoding(error)
+    }
+}
+
+impl From<zip::result::ZipError> for FestError {
+    fn from(error: zip::result::ZipError) -> Self {
+        match error {
+            zip::result::ZipError::Io(e) => FestError::Io(e),
+            e => FestError::Archive(e),
+        }
+    }
+}
+
+impl From<roxmltree::Error> for FestError {
+    fn from(error: roxmltree::Error) -> Self {
+        let position = error.pos();
+        FestError::xml(position.row, position.col, error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xml_error() {
+        let error: FestError = roxmltree::Document::parse("<FEST>\n<Kat a=>").unwrap_err().into();
+
+        match &error {
+            FestError::Xml { line, .. } => assert_eq!(*line, 2),
+            _ => panic!("Expected a xml error"),
+        }
+        assert!(error.source().is_some());
+        assert!(error.to_string().starts_with("Invalid xml at line 2"));
+    }
+
+    #[test]
+    fn test_io_error() {
+        let error: FestError = std::fs::read("does_not_exist.xml").unwrap_err().into();
+
+        assert!(matches!(error, FestError::Io(_)));
+        assert!(error.source().is_some());
+    }
+}
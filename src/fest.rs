@@ -1,33 +1,298 @@
 use std::fs;
-use std::error::Error;
-use crate::types::{Package, Interaction, LastUpdate};
+use std::io::{BufWriter, Cursor, Read};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use crate::types::{ActiveSubstance, Package, Interaction, LastUpdate, SubstanceStrength};
+use crate::archive;
+use crate::atc::AtcTree;
+use crate::catalog::Catalog;
+use crate::complete::Completion;
+use crate::error::FestError;
+use crate::generic::GenericOptions;
+use crate::index::{EntryPosition, Index};
+use crate::lookup::{self, EntryRef, Identifier};
+use crate::kith::Timestamp;
+use crate::query::PackageQuery;
+use crate::release::ReleaseInfo;
+use crate::report::{self, ParseReport};
+use crate::schema::SchemaVersion;
+use crate::search::{self, SearchResult};
+use crate::snapshot::{self, SnapshotInfo};
+use crate::stats::{self, Count};
+use crate::substitute::{self, Substitute};
+use crate::validate::{self, Violation};
 use crate::xml;
 
+/// Options for loading the fest file
+///
+/// # Example
+/// ```
+/// use festlib::{Fest, LoadOptions};
+///
+/// let options = LoadOptions::new().warnings_as_errors(true);
+/// let fest = Fest::with_options("test_fest.xml", &options).unwrap();
+///
+/// assert!(fest.report().is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct LoadOptions {
+    warnings_as_errors: bool,
+    catalogs: Vec<Catalog>,
+}
+
+impl LoadOptions {
+    pub fn new() -> Self {
+        LoadOptions::default()
+    }
+
+    /// Fail with FestError::Warnings if the parse report has warnings
+    pub fn warnings_as_errors(mut self, value: bool) -> Self {
+        self.warnings_as_errors = value;
+        self
+    }
+
+    /// The catalogs to parse. All catalogs are parsed by default.
+    pub fn catalogs(mut self, catalogs: &[Catalog]) -> Self {
+        self.catalogs = catalogs.to_vec();
+        self
+    }
+
+    /// Parse the catalog, in addition to the chosen catalogs
+    pub fn catalog(mut self, catalog: Catalog) -> Self {
+        if !self.catalogs.contains(&catalog) {
+            self.catalogs.push(catalog);
+        }
+        self
+    }
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        LoadOptions {
+            warnings_as_errors: false,
+            catalogs: Catalog::all(),
+        }
+    }
+}
+
 /// Container for the fest file
+///
+/// Fest can be serialized and deserialized with serde, so a parsed
+/// catalog can be sent to other services without the xml file.
+/// The xml content is not kept after the file is parsed.
+#[derive(Serialize, Deserialize)]
 pub struct Fest {
-    _filename: String,
-    pub content: String, // TODO: remove the test, so we dont need pub
+    delivery_date: LastUpdate,
     packages: Vec<Package>,
     interactions: Vec<Interaction>,
+    #[serde(default)]
+    active_substances: Vec<ActiveSubstance>,
+    #[serde(default)]
+    substance_strengths: Vec<SubstanceStrength>,
+    #[serde(default)]
+    release: ReleaseInfo,
+    #[serde(default)]
+    report: ParseReport,
+    #[serde(default = "Catalog::all")]
+    catalogs: Vec<Catalog>,
+    #[serde(skip)]
+    index: OnceLock<Index>,
 }
 
 impl Fest {
     /// Constructor for a new instance of the fest file
-    pub fn new(filename: &str) -> Result<Self, Box<dyn Error>> {
-        let content = Fest::read_file(filename)?;
-        let document = xml::document(&content);
+    ///
+    /// The file can either be the xml file or the zip archive
+    /// from DMP (fest251_inst.zip).
+    pub fn new<P: AsRef<Path>>(filename: P) -> Result<Self, FestError> {
+        Fest::with_options(filename, &LoadOptions::default())
+    }
+
+    /// Constructor for the fest file with options for how it is loaded
+    pub fn with_options<P: AsRef<Path>>(filename: P, options: &LoadOptions) -> Result<Self, FestError> {
+        let content = Fest::read_file(filename.as_ref())?;
+        Fest::parse(Some(filename.as_ref()), content, options)
+    }
+
+    /// Reads the fest xml file from the zip archive distributed
+    /// by DMP (fest251_inst.zip), without unpacking it to disk.
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::Fest;
+    ///
+    /// let fest = Fest::from_zip("test_fest.zip").unwrap();
+    ///
+    /// assert_eq!(fest.packages().len(), 5);
+    /// ```
+    pub fn from_zip<P: AsRef<Path>>(filename: P) -> Result<Self, FestError> {
+        let content = archive::read_xml(fs::File::open(filename.as_ref())?)?;
+        Fest::parse(Some(filename.as_ref()), content, &LoadOptions::default())
+    }
 
-        let packages = xml::packages(&document);
-        let interactions = xml::interactions(&document);
+    /// Reads the fest xml file, or the zip archive, from a reader
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::Fest;
+    ///
+    /// let file = std::fs::File::open("test_fest.xml").unwrap();
+    /// let fest = Fest::from_reader(file).unwrap();
+    ///
+    /// assert_eq!(fest.packages().len(), 5);
+    /// ```
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, FestError> {
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content)?;
+
+        Fest::from_bytes(&content)
+    }
+
+    /// Reads the fest xml file, or the zip archive, from memory
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::Fest;
+    ///
+    /// let content = std::fs::read("test_fest.zip").unwrap();
+    /// let fest = Fest::from_bytes(&content).unwrap();
+    ///
+    /// assert_eq!(fest.packages().len(), 5);
+    /// ```
+    pub fn from_bytes(content: &[u8]) -> Result<Self, FestError> {
+        Fest::from_bytes_with_options(content, &LoadOptions::default())
+    }
+
+    /// Reads the fest xml file, or the zip archive, from memory with
+    /// options for how it is loaded
+    pub fn from_bytes_with_options(content: &[u8], options: &LoadOptions) -> Result<Self, FestError> {
+        let content = if archive::is_zip(content) {
+            archive::read_xml(Cursor::new(content))?
+        } else {
+            String::from_utf8(content.to_vec())?
+        };
+
+        Fest::parse(None, content, options)
+    }
+
+    fn parse(source: Option<&Path>, content: String, options: &LoadOptions) -> Result<Self, FestError> {
+        let start = Instant::now();
+        let document = xml::document(&content)?;
+        let schema_version = SchemaVersion::detect(&document)?;
+        let catalogs = &options.catalogs;
+        let required: Vec<&str> = catalogs.iter().filter(|c| c.is_required()).map(|c| c.name()).collect();
+        xml::check(&document, &required)?;
+
+        let hentet_dato = xml::delivery_date(&document);
+        let delivery_date = LastUpdate::new(hentet_dato.as_deref());
+
+        // the parsers record the entries they skip, each in its own
+        // report since they run in parallel
+        let mut reports: [ParseReport; 4] = Default::default();
+        let [r1, r2, r3, r4] = &mut reports;
+
+        // the interactions and substances are the same in all versions
+        let (packages, (interactions, (active_substances, substance_strengths))) = join(
+            || load(catalogs, Catalog::Packages, || schema_version.packages(&document, r1)),
+            || join(
+                || load(catalogs, Catalog::Interactions, || xml::interactions(&document, r2)),
+                || join(
+                    || load(catalogs, Catalog::SubstanceStrengths, || xml::active_substances(&document, r3)),
+                    || load(catalogs, Catalog::SubstanceStrengths, || xml::substance_strengths(&document, r4)),
+                ),
+            ),
+        );
+
+        let mut report = report::check(&document, catalogs);
+        for skipped in reports {
+            report.merge(skipped);
+        }
+        if options.warnings_as_errors && !report.is_empty() {
+            return Err(FestError::Warnings(report));
+        }
+
+        // the indexes are part of the load duration
+        let index = Index::new(&packages, &interactions, &active_substances, &substance_strengths);
+
+        let release = ReleaseInfo::from(
+            hentet_dato.as_deref().and_then(Timestamp::parse),
+            schema_version,
+            source.map(Path::to_path_buf),
+            snapshot::content_hash(content.as_bytes()),
+            catalogs.iter().map(|c| (*c, xml::entry_count(&document, *c))).collect(),
+            start.elapsed(),
+        );
 
         Ok(Fest {
-            _filename: filename.to_string(),
-            content,
+            delivery_date,
             packages,
             interactions,
+            active_substances,
+            substance_strengths,
+            release,
+            report,
+            catalogs: catalogs.clone(),
+            index: OnceLock::from(index),
         })
     }
 
+    /// Returns an error if the catalog wasn't loaded, see [`FestBuilder`].
+    /// The finders return the same error when they need a catalog that
+    /// isn't loaded, so an empty result always means nothing was found.
+    ///
+    /// [`FestBuilder`]: crate::FestBuilder
+    pub fn require(&self, catalog: Catalog) -> Result<(), FestError> {
+        match self.catalogs.contains(&catalog) {
+            true => Ok(()),
+            false => Err(FestError::CatalogNotLoaded(catalog)),
+        }
+    }
+
+    /// Retrieve the catalogs that were loaded
+    pub fn catalogs(&self) -> &[Catalog] {
+        &self.catalogs
+    }
+
+    /// Retrieve the version of the fest message (M30)
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::{Fest, SchemaVersion};
+    ///
+    /// let fest = Fest::new("test_fest.xml").unwrap();
+    ///
+    /// assert_eq!(fest.schema_version(), SchemaVersion::V2_5_1);
+    /// ```
+    pub fn schema_version(&self) -> SchemaVersion {
+        self.release.schema_version()
+    }
+
+    /// Retrieve the information about the fest file, captured when
+    /// it was loaded
+    pub fn release(&self) -> &ReleaseInfo {
+        &self.release
+    }
+
+    /// Retrieve the warnings found while parsing the fest file, e.g.
+    /// missing required fields and entries that were skipped.
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::Fest;
+    ///
+    /// let fest = Fest::new("test_fest.xml").unwrap();
+    ///
+    /// for warning in fest.report().warnings() {
+    ///     println!("{}", warning);
+    /// }
+    /// ```
+    pub fn report(&self) -> &ParseReport {
+        &self.report
+    }
+
     /// Retrieve the last update for the fest xml file
     ///
     /// # example
@@ -37,11 +302,10 @@ impl Fest {
     /// let fest = Fest::new("test_fest.xml").unwrap();
     /// let date = fest.delivery_date();
     ///
-    /// assert_eq!(date.date(), "2024-09-09T14:21:28");
+    /// assert_eq!(date.date(), Some("2024-09-09T14:21:28"));
     /// ```
-    pub fn delivery_date(&self) -> LastUpdate {
-
-        LastUpdate::new(&xml::delivery_date(&self.content))
+    pub fn delivery_date(&self) -> &LastUpdate {
+        &self.delivery_date
     }
 
     /// Retrieve all drug packages from fest. (OppfLegemiddelpakning)
@@ -58,151 +322,658 @@ impl Fest {
     ///
     /// assert_eq!(packages.len(), 5);
     /// ```
-    pub fn packages(&self) -> &Vec<Package> {
+    pub fn packages(&self) -> &[Package] {
         &self.packages
     }
 
+    /// Retrieve all interactions from fest. (OppfInteraksjon)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use festlib::Fest;
+    ///
+    /// let fest = Fest::new("test_fest.xml").unwrap();
+    ///
+    /// assert_eq!(fest.interactions().len(), 1);
+    /// ```
+    pub fn interactions(&self) -> &[Interaction] {
+        &self.interactions
+    }
+
+    /// Retrieve all active substances from fest, with the id that
+    /// RefVirkestoff refers to and the name. (OppfVirkestoff)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use festlib::Fest;
+    ///
+    /// let fest = Fest::new("test_fest.xml").unwrap();
+    ///
+    /// assert_eq!(fest.active_substances()[0].name(), "Test Substance A");
+    /// ```
+    pub fn active_substances(&self) -> &[ActiveSubstance] {
+        &self.active_substances
+    }
+
+    /// Retrieve all active substances with strength from fest. (OppfVirkestoff)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use festlib::Fest;
+    ///
+    /// let fest = Fest::new("test_fest.xml").unwrap();
+    /// let strengths = fest.substance_strengths();
+    ///
+    /// assert_eq!(strengths[0].strength().to_string(), "10 mg");
+    /// ```
+    pub fn substance_strengths(&self) -> &[SubstanceStrength] {
+        &self.substance_strengths
+    }
+
     /// Search for a package with itemnumber
     ///
     /// # Example
     /// ```
     /// use festlib::Fest;
     /// let fest = Fest::new("test_fest.xml").unwrap();
-    /// let result = fest.find_package("061561");
+    /// let result = fest.find_package("061561").unwrap();
     ///
     /// assert_eq!(result.unwrap().itemnum(), "061561");
     /// ```
-    pub fn find_package(&self, itemnum: &str) -> Option<&Package> {
-       self.packages().iter().find(|p| p.itemnum() == itemnum)
+    pub fn find_package(&self, itemnum: &str) -> Result<Option<&Package>, FestError> {
+        self.require(Catalog::Packages)?;
+
+        Ok(self.index().itemnum(itemnum).map(|i| &self.packages[i]))
     }
 
-    /// Search for generic products of a Package
+    /// Search for a package with the EAN/GTIN barcode. GTIN-14 with a
+    /// leading zero, and GTIN-12, are matched with the EAN-13 in FEST.
     ///
     /// # Example
     /// ```
     /// use festlib::Fest;
     /// let fest = Fest::new("test_fest.xml").unwrap();
-    /// let package = fest.find_package("061561").unwrap();
     ///
-    /// let result = fest.find_generic(&package);
+    /// let package = fest.find_package_by_ean("07001234567890").unwrap().unwrap();
+    /// assert_eq!(package.itemnum(), "061561");
     /// ```
-    pub fn find_generic(&self, package: &Package) -> Option<Vec<&Package>> {
+    pub fn find_package_by_ean(&self, ean: &str) -> Result<Option<&Package>, FestError> {
+        self.require(Catalog::Packages)?;
 
-        // if the package dont have any id theres no geneirc products for it
-        if package.exchange_id().is_none() {
-            return None;
-        }
+        Ok(self.index().ean(&lookup::normalize_ean(ean)).map(|i| &self.packages[i]))
+    }
 
-        let result: Vec<&Package> = self.packages
-            .iter()
-            .filter(|p|
-                p.exchange_id() ==
-                package.exchange_id())
-            .collect();
+    /// Search for a package with the id of <Legemiddelpakning>
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::Fest;
+    /// let fest = Fest::new("test_fest.xml").unwrap();
+    ///
+    /// let package = fest.find_package_by_id("ID_0138BA04-7B67-4FB5-B44D-7491336CAF20").unwrap().unwrap();
+    /// assert_eq!(package.itemnum(), "061561");
+    /// ```
+    pub fn find_package_by_id(&self, id: &str) -> Result<Option<&Package>, FestError> {
+        self.require(Catalog::Packages)?;
 
-        if result.len() > 0 {
-            Some(result)
-        } else {
-            None
+        Ok(self.index().package_id(id.trim()).map(|i| &self.packages[i]))
+    }
+
+    /// Search for an entry in any of the catalogs with the Oppf id
+    /// (Metadata::id). Only the catalogs that are loaded are searched.
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::{EntryRef, Fest};
+    /// let fest = Fest::new("test_fest.xml").unwrap();
+    ///
+    /// match fest.find_entry_by_oppf_id("ID_F994748F-3A21-4FC3-9964-DBE097924A75") {
+    ///     Some(EntryRef::Package(package)) => assert_eq!(package.itemnum(), "061561"),
+    ///     _ => panic!("Expected a package"),
+    /// }
+    /// ```
+    pub fn find_entry_by_oppf_id(&self, id: &str) -> Option<EntryRef<'_>> {
+        let entry = match self.index().entry_id(id.trim())? {
+            EntryPosition::Package(i) => EntryRef::Package(&self.packages[i]),
+            EntryPosition::Interaction(i) => EntryRef::Interaction(&self.interactions[i]),
+            EntryPosition::ActiveSubstance(i) => EntryRef::ActiveSubstance(&self.active_substances[i]),
+            EntryPosition::SubstanceStrength(i) => EntryRef::SubstanceStrength(&self.substance_strengths[i]),
+        };
+
+        Some(entry)
+    }
+
+    /// Search for a package with any identifier: itemnumber, EAN, the
+    /// package id or the Oppf id. The kind is detected from the format,
+    /// see [`Identifier::detect`].
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::Fest;
+    /// let fest = Fest::new("test_fest.xml").unwrap();
+    ///
+    /// for id in ["061561", "7001234567890", "ID_F994748F-3A21-4FC3-9964-DBE097924A75"] {
+    ///     assert_eq!(fest.find_package_any(id).unwrap().unwrap().itemnum(), "061561");
+    /// }
+    /// ```
+    pub fn find_package_any(&self, identifier: &str) -> Result<Option<&Package>, FestError> {
+        self.require(Catalog::Packages)?;
+        let identifier = identifier.trim();
+
+        match Identifier::detect(identifier) {
+            Some(Identifier::Itemnum) => self.find_package(identifier),
+            Some(Identifier::Ean) => self.find_package_by_ean(identifier),
+            Some(Identifier::Id) => Ok(self.find_package_by_id(identifier)?.or_else(|| {
+                match self.find_entry_by_oppf_id(identifier)? {
+                    EntryRef::Package(package) => Some(package),
+                    _ => None,
+                }
+            })),
+            None => Ok(None),
         }
     }
 
-    /// Search for interactions for two or more packages
+    /// Search for all packages with the ATC code
     ///
-    /// Will fail if called with vector smaller than 2.
+    /// # Example
+    /// ```
+    /// use festlib::Fest;
+    /// let fest = Fest::new("test_fest.xml").unwrap();
+    ///
+    /// let mut packages = fest.find_packages_by_atc("A01AA01").unwrap();
+    /// assert!(packages.all(|p| p.atc().unwrap().v() == Some("A01AA01")));
+    /// ```
+    pub fn find_packages_by_atc(&self, atc: &str) -> Result<impl Iterator<Item = &Package>, FestError> {
+        self.require(Catalog::Packages)?;
+
+        Ok(self.index().atc(atc).iter().map(|i| &self.packages[*i]))
+    }
+
+    /// Runs the query over the packages, see [`PackageQuery`]
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::{Fest, PackageQuery, Timestamp};
+    /// let fest = Fest::new("test_fest.xml").unwrap();
+    ///
+    /// let query = PackageQuery::new()
+    ///     .atc_prefix("A01")
+    ///     .has_exchange_group(true)
+    ///     .marketed_on(Timestamp::from_date(2024, 9, 9));
+    ///
+    /// for package in fest.query(&query).unwrap() {
+    ///     assert!(package.exchange_id().is_some());
+    /// }
+    /// ```
+    pub fn query(&self, query: &PackageQuery) -> Result<impl Iterator<Item = &Package>, FestError> {
+        self.require(Catalog::Packages)?;
+
+        Ok(query.run(&self.packages, self.index()).into_iter())
+    }
+
+    /// Search for packages by the name (NavnFormStyrke), which starts
+    /// with the brand name. Upper and lower case are the same, æ, ø and
+    /// å can be written as ae, oe and aa, and small typos are tolerated.
+    /// Every word in the query must match, and the best match is first.
     ///
     /// # Example
     /// ```
     /// use festlib::Fest;
     /// let fest = Fest::new("test_fest.xml").unwrap();
     ///
-    /// let package1 = fest.find_package("061561").unwrap();
-    /// let package2 = fest.find_package("017701").unwrap();
+    /// let results: Vec<_> = fest.search("test medcine 10").unwrap().collect();
+    /// assert_eq!(results[0].package().name(), "Test Medicine 10mg Kapsel");
+    /// assert!(results.iter().all(|r| r.package().name().contains("10mg")));
+    /// ```
+    pub fn search(&self, query: &str) -> Result<impl Iterator<Item = SearchResult<'_>>, FestError> {
+        self.require(Catalog::Packages)?;
+
+        Ok(search::search(&self.packages, query).into_iter())
+    }
+
+    /// Completes the start of a package name, substance name or ATC
+    /// group (name or code), for suggestions while the user types.
+    /// Returns at most `limit` completions: the ones that are typed in
+    /// full first, then the shortest. The completions are indexed when
+    /// the file is loaded, from the packages and the substances, so both
+    /// catalogs must be loaded.
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::{CompletionKind, Fest};
+    /// let fest = Fest::new("test_fest.xml").unwrap();
     ///
-    /// let check_interaction = vec![package1, package2];
-    /// let interaction = fest.find_interaction(&check_interaction);
+    /// let completion = fest.complete("test med", 5).unwrap().next().unwrap();
+    /// assert_eq!(completion.text(), "Test Medicine 10mg Kapsel");
+    /// assert_eq!(completion.kind(), CompletionKind::Brand);
     ///
+    /// let atc = fest.complete("A01AA0", 5).unwrap().next().unwrap();
+    /// assert_eq!(atc.code(), Some("A01AA01"));
     /// ```
-    pub fn find_interaction(&self, packages: &Vec<&Package>) -> Option<Vec<&Interaction>> {
-        // TODO: maybe just return None, since there is no drug that have an interaction with
-        // itself.
-        assert!(packages.len() > 1);
+    pub fn complete(&self, prefix: &str, limit: usize) -> Result<impl Iterator<Item = &Completion>, FestError> {
+        self.require(Catalog::Packages)?;
+        self.require(Catalog::SubstanceStrengths)?;
 
-        let mut result = Vec::new();
+        Ok(self.index().completions().complete(prefix, limit).into_iter())
+    }
+
+    /// Search for generic products of a Package: the packages in the
+    /// same exchange group, including the package itself. Packages
+    /// without an exchange group have none.
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::Fest;
+    /// let fest = Fest::new("test_fest.xml").unwrap();
+    /// let package = fest.find_package("061561").unwrap().unwrap();
+    ///
+    /// assert_eq!(fest.find_generic(package).unwrap().count(), 4);
+    /// ```
+    pub fn find_generic(&self, package: &Package) -> Result<impl Iterator<Item = &Package>, FestError> {
+        self.find_generic_with(package, &GenericOptions::default())
+    }
+
+    /// Search for generic products of a Package with options, e.g. only
+    /// the packages in the exchange group and on the market at a date.
+    /// Without an `as_of` date, `only_marketed` checks the market and the
+    /// exchange group at the date the fest file was made.
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::{Fest, GenericOptions, Timestamp};
+    /// let fest = Fest::new("test_fest.xml").unwrap();
+    /// let package = fest.find_package("061561").unwrap().unwrap();
+    ///
+    /// let options = GenericOptions::substitution(Timestamp::from_date(2025, 6, 1));
+    /// let generic = fest.find_generic_with(package, &options).unwrap().next().unwrap();
+    /// assert_eq!(generic.itemnum(), "654321");
+    /// ```
+    pub fn find_generic_with(&self, package: &Package, options: &GenericOptions) -> Result<impl Iterator<Item = &Package>, FestError> {
+        self.require(Catalog::Packages)?;
+        let group = match package.exchange_id() {
+            Some(id) => self.index().exchange_group(id),
+            None => &[],
+        };
+        let today = self.release.delivery_date();
+
+        // exchange groups are small, so the matches are collected to not
+        // borrow the package in the iterator
+        let generics: Vec<&Package> = group
+            .iter()
+            .map(|i| &self.packages[*i])
+            .filter(|p| options.matches(package, p, today.as_ref()))
+            .collect();
+
+        Ok(generics.into_iter())
+    }
+
+    /// Finds the substitutes for a Package at the date, cheapest first:
+    /// the active packages in the same exchange group that are on the
+    /// market and have the same pack size (Mengde), including the
+    /// package itself. The packages with a step price (Trinnpris) are
+    /// ranked by it and come first, then the packages ranked by AUP.
+    /// [`Substitute::basis`] tells which price is used. Packages without
+    /// a price at the date are left out.
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::{Fest, PriceBasis, Timestamp};
+    /// let fest = Fest::new("test_fest.xml").unwrap();
+    /// let package = fest.find_package("061561").unwrap().unwrap();
+    ///
+    /// let cheapest = fest.cheapest_substitute(package, Timestamp::from_date(2025, 6, 1)).unwrap().next().unwrap();
+    /// assert_eq!(cheapest.package().itemnum(), "654321");
+    /// assert_eq!(cheapest.basis(), PriceBasis::StepPrice);
+    /// ```
+    pub fn cheapest_substitute(&self, package: &Package, as_of: Timestamp) -> Result<impl Iterator<Item = Substitute<'_>>, FestError> {
+        let options = GenericOptions::substitution(as_of).include_self(true).same_pack_size(true);
+
+        Ok(substitute::rank(self.find_generic_with(package, &options)?, &as_of).into_iter())
+    }
+
+    /// Search for interactions between the packages, in file order.
+    /// An interaction is found when two or more of its substances have
+    /// the ATC code of one of the packages.
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::Fest;
+    /// let fest = Fest::new("test_fest.xml").unwrap();
+    ///
+    /// let package1 = fest.find_package("061561").unwrap().unwrap();
+    /// let package2 = fest.find_package("017701").unwrap().unwrap();
+    ///
+    /// let interactions = fest.find_interaction(&[package1, package2]).unwrap();
+    /// assert_eq!(interactions.count(), 1);
+    /// ```
+    pub fn find_interaction(&self, packages: &[&Package]) -> Result<impl Iterator<Item = &Interaction>, FestError> {
+        self.require(Catalog::Interactions)?;
 
         // extract the package atc codes and remove duplicates
-        let mut atc_codes: Vec<&String> = packages.iter().map(|p| p.atc().v()).collect();
+        let mut atc_codes: Vec<&str> = packages.iter().filter_map(|p| p.atc()?.v()).collect();
+        atc_codes.sort_unstable();
         atc_codes.dedup();
 
-        // first search all interactions for the atc code.
-        // if vector > 2 search
+        // the interactions with a substance for one of our atc codes,
+        // in file order
+        let mut collection: Vec<usize> = atc_codes
+            .iter()
+            .flat_map(|a| self.index().interaction_atc(a))
+            .copied()
+            .collect();
+        collection.sort_unstable();
+        collection.dedup();
 
-        let interactions = &self.interactions;
-        let mut collection = Vec::new();
+        // if there is more than 2 matches within the interaction
+        // we have an interaction
+        collection.retain(|i| {
+            let count = self.interactions[*i]
+                .substances()
+                .iter()
+                .filter(|s| s.atc().is_some_and(|a| atc_codes.contains(&a)))
+                .count();
+            count > 1
+        });
 
-        // find all matching interaction for our atc codes and store them in
-        // a vector
-        for i in interactions {
-            for s in i.substances() {
-                for a in &atc_codes {
-                    if *a == s.atc() {
-                        collection.push(i);
-                    }
-                }
-            }
-        }
+        Ok(collection.into_iter().map(|i| &self.interactions[i]))
+    }
 
-        // find all matching atc codes within our collected interactions
-        // and if there is more than 2 matches we have an interaction
-        for c in collection.clone() {
-            let mut count = 0;
-            for a in &atc_codes {
-                for s in c.substances() {
-                    if s.atc() == *a {
-                        count += 1;
-                    }
-                }
-                if count > 1 {
-                    result.push(c);
-                }
-            }
-        }
+    /// Builds the ATC hierarchy from the ATC codes of the packages and
+    /// the substances with strength, with the counts for each group
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::Fest;
+    /// let fest = Fest::new("test_fest.xml").unwrap();
+    ///
+    /// for group in fest.atc_tree().roots() {
+    ///     println!("{} {} packages", group.code(), group.package_count());
+    /// }
+    /// ```
+    pub fn atc_tree(&self) -> AtcTree {
+        AtcTree::new(&self.packages, &self.substance_strengths)
+    }
 
-        // TODO: maybe we should store the result in a map?
-        // clear our result with dublicate interactions
-        result.dedup_by_key(|r| r.id().clone());
+    /// Counts the packages in each ATC group on the level, from 1
+    /// (anatomical main group) to 5 (chemical substance)
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::Fest;
+    /// let fest = Fest::new("test_fest.xml").unwrap();
+    ///
+    /// let counts = fest.count_by_atc(1);
+    /// assert_eq!((counts[0].code(), counts[0].count()), ("A", 4));
+    /// ```
+    pub fn count_by_atc(&self, level: u8) -> Vec<Count> {
+        stats::count_by_atc(&self.atc_tree(), level)
+    }
 
-        if result.len() > 0 {
-            Some(result)
-        } else {
-            None
+    /// Counts the packages in each prescription group (Reseptgruppe)
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::Fest;
+    /// let fest = Fest::new("test_fest.xml").unwrap();
+    ///
+    /// let counts = fest.count_by_prescription_group();
+    /// assert_eq!((counts[0].code(), counts[0].count()), ("A", 5));
+    /// ```
+    pub fn count_by_prescription_group(&self) -> Vec<Count> {
+        stats::count_by(&self.packages, |p| p.group().and_then(|g| Some((g.v()?, g.dn()))))
+    }
+
+    /// Counts the packages with each dosage form (LegemiddelformKort)
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::Fest;
+    /// let fest = Fest::new("test_fest.xml").unwrap();
+    ///
+    /// for count in fest.count_by_form() {
+    ///     println!("{:?}: {}", count.name(), count.count());
+    /// }
+    /// ```
+    pub fn count_by_form(&self) -> Vec<Count> {
+        stats::count_by(&self.packages, |p| p.form().and_then(|f| Some((f.v()?, f.dn()))))
+    }
+
+    // the indexes are built on the first lookup after deserializing
+    fn index(&self) -> &Index {
+        self.index.get_or_init(|| Index::new(&self.packages, &self.interactions, &self.active_substances, &self.substance_strengths))
+    }
+
+    /// Saves the parsed fest as a binary snapshot, which is much faster
+    /// to load than the xml file.
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::Fest;
+    ///
+    /// let fest = Fest::new("test_fest.xml").unwrap();
+    /// let path = std::env::temp_dir().join("festlib_doc.snapshot");
+    /// fest.save_snapshot(&path).unwrap();
+    ///
+    /// let snapshot = Fest::load_snapshot(&path).unwrap();
+    /// assert_eq!(snapshot.packages().len(), 5);
+    /// ```
+    pub fn save_snapshot<P: AsRef<Path>>(&self, path: P) -> Result<(), FestError> {
+        let file = fs::File::create(path)?;
+        snapshot::write(BufWriter::new(file), self)
+    }
+
+    /// Loads a snapshot saved with [`Fest::save_snapshot`]. The snapshot
+    /// is rejected if it was made by another version of festlib.
+    pub fn load_snapshot<P: AsRef<Path>>(path: P) -> Result<Self, FestError> {
+        Fest::from_snapshot(&fs::read(path)?)
+    }
+
+    /// Reads the snapshot from a byte buffer, e.g. one that was
+    /// downloaded or embedded in the binary
+    pub fn from_snapshot(bytes: &[u8]) -> Result<Self, FestError> {
+        snapshot::read(bytes)
+    }
+
+    /// Reads the header of the snapshot, without deserializing the fest.
+    /// Use it to check if the snapshot is made from the current fest file.
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::Fest;
+    ///
+    /// let fest = Fest::new("test_fest.xml").unwrap();
+    /// let path = std::env::temp_dir().join("festlib_doc_info.snapshot");
+    /// fest.save_snapshot(&path).unwrap();
+    ///
+    /// let info = Fest::snapshot_info(&path).unwrap();
+    /// assert_eq!(info.delivery_date(), Some("2024-09-09T14:21:28"));
+    /// assert_eq!(info.content_hash(), fest.content_hash());
+    /// ```
+    pub fn snapshot_info<P: AsRef<Path>>(path: P) -> Result<SnapshotInfo, FestError> {
+        let bytes = fs::read(path)?;
+        Ok(snapshot::info(&bytes)?.0)
+    }
+
+    /// Retrieve the hash of the xml content. Zip archives are hashed
+    /// after the xml is read from the archive.
+    pub fn content_hash(&self) -> u64 {
+        self.release.content_hash()
+    }
+
+    /// Validates the fest file against the M30 schema, without parsing
+    /// it. Checks the required elements, cardinalities, code values and
+    /// oids in the catalogs festlib reads, and the KITH datatypes.
+    /// Returns an error if the file isn't xml or isn't a fest file.
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::Fest;
+    ///
+    /// let violations = Fest::validate("test_fest.xml").unwrap();
+    ///
+    /// for violation in &violations {
+    ///     println!("{}", violation);
+    /// }
+    /// assert!(violations.is_empty());
+    /// ```
+    pub fn validate<P: AsRef<Path>>(filename: P) -> Result<Vec<Violation>, FestError> {
+        let content = Fest::read_file(filename.as_ref())?;
+        let document = xml::document(&content)?;
+        let schema_version = SchemaVersion::detect(&document)?;
+
+        Ok(validate::validate(&document, schema_version))
+    }
+
+    // reads the xml file, or the xml inside it if the file is a zip archive
+    fn read_file(file: &Path) -> Result<String, FestError> {
+        let file_content = fs::read(file)?;
+
+        if archive::is_zip(&file_content) {
+            return archive::read_xml(Cursor::new(file_content));
         }
+
+        Ok(String::from_utf8(file_content)?)
+    }
+}
+
+// parses the catalog if it is chosen
+fn load<T>(catalogs: &[Catalog], catalog: Catalog, parse: impl FnOnce() -> Vec<T>) -> Vec<T> {
+    if catalogs.contains(&catalog) {
+        parse()
+    } else {
+        Vec::new()
     }
+}
+
+// parses the catalogs across threads with the parallel feature
+#[cfg(feature = "parallel")]
+use rayon::join;
+
+#[cfg(not(feature = "parallel"))]
+fn join<A, B>(a: impl FnOnce() -> A, b: impl FnOnce() -> B) -> (A, B) {
+    (a(), b())
+}
 
-    fn read_file(file: &str) -> Result<String, Box<dyn Error>> {
-        let file_content = fs::read_to_string(file)?;
+impl FromStr for Fest {
+    type Err = FestError;
 
-        Ok(file_content)
+    /// Parses the content of the fest xml file
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::Fest;
+    ///
+    /// let fest: Fest = include_str!("../test_fest.xml").parse().unwrap();
+    ///
+    /// assert_eq!(fest.packages().len(), 5);
+    /// ```
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        Fest::parse(None, content.to_string(), &LoadOptions::default())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::substitute::PriceBasis;
 
     #[test]
     fn test_read_file() {
-        let file = Fest::read_file("test_fest.xml");
+        let file = Fest::read_file(Path::new("test_fest.xml"));
         assert!(file.is_ok());
     }
 
+    #[test]
+    fn test_read_zip_file() {
+        let file = Fest::read_file(Path::new("test_fest.zip")).unwrap();
+        assert_eq!(file, Fest::read_file(Path::new("test_fest.xml")).unwrap());
+    }
+
+    #[test]
+    fn test_from_str() {
+        let fest = Fest::from_str(&fs::read_to_string("test_fest.xml").unwrap()).unwrap();
+        assert_eq!(fest.packages().len(), 5);
+        assert_eq!(fest.delivery_date().date(), Some("2024-09-09T14:21:28"));
+    }
+
+    #[test]
+    fn test_from_bytes_invalid_utf8() {
+        assert!(matches!(Fest::from_bytes(&[0xff, 0xfe, 0x00]), Err(FestError::Encoding(_))));
+    }
+
+    #[test]
+    fn test_errors() {
+        let content = fs::read_to_string("test_fest.xml").unwrap();
+
+        // a truncated download
+        let result = Fest::from_str(&content[..content.len() / 2]);
+        assert!(matches!(result, Err(FestError::Xml { .. })));
+
+        let result = Fest::from_str("<FEST><KatLegemiddelpakning/></FEST>");
+        assert!(matches!(result, Err(FestError::MissingCatalog(c)) if c == "KatInteraksjon"));
+
+        assert!(matches!(Fest::new("does_not_exist.xml"), Err(FestError::Io(_))));
+    }
+
+    #[test]
+    fn test_namespace() {
+        let content = fs::read_to_string("test_fest.xml").unwrap().replace(
+            "<FEST>",
+            r#"<FEST xmlns="http://www.kith.no/xmlstds/eresept/m30/2014-12-01">"#,
+        ).replace(
+            "<Legemiddelpakning>",
+            r#"<Legemiddelpakning xmlns="http://www.kith.no/xmlstds/eresept/forskrivning/2014-12-01">"#,
+        );
+
+        let fest = Fest::from_str(&content).unwrap();
+        assert_eq!(fest.schema_version(), SchemaVersion::V2_5_1);
+        assert_eq!(fest.packages().len(), 5);
+        assert_eq!(fest.find_package("061561").unwrap().unwrap().ean().unwrap(), "7001234567890");
+        assert!(fest.report().is_empty());
+
+        let content = content.replace("m30/2014-12-01", "m30/2099-01-01");
+        assert!(matches!(Fest::from_str(&content), Err(FestError::UnsupportedSchema(_))));
+    }
+
+    #[test]
+    fn test_legacy_version() {
+        let fest = Fest::new("test_fest_v25.xml").unwrap();
+        assert_eq!(fest.schema_version(), SchemaVersion::V2_5);
+        assert_eq!(fest.delivery_date().date(), Some("2015-06-01T10:15:00"));
+        assert_eq!(fest.packages().len(), 2);
+        assert!(fest.report().is_empty(), "{:?}", fest.report());
+
+        let package = fest.find_package("061561").unwrap().unwrap();
+        assert_eq!(package.quantity().unwrap().to_string(), "28 stk");
+
+        let package2 = fest.find_package("017701").unwrap().unwrap();
+        let interactions = fest.find_interaction(&[package, package2]).unwrap();
+        assert_eq!(interactions.count(), 1);
+    }
+
+    #[test]
+    fn test_warnings_as_errors() {
+        let content = "<FEST><KatLegemiddelpakning><OppfLegemiddelpakning/></KatLegemiddelpakning><KatInteraksjon/></FEST>";
+
+        let fest = Fest::parse(None, content.to_string(), &LoadOptions::new()).unwrap();
+        assert_eq!(fest.packages().len(), 0);
+        assert!(!fest.report().is_empty());
+
+        let options = LoadOptions::new().warnings_as_errors(true);
+        let result = Fest::parse(None, content.to_string(), &options);
+        assert!(matches!(result, Err(FestError::Warnings(r)) if r.len() == fest.report().len()));
+    }
+
     #[test]
     fn test_hentetdato() {
         let fest = Fest::new("test_fest.xml").unwrap();
         let date = fest.delivery_date();
 
         //let res = file.delivery_date().unwrap().unwrap();
-        assert_eq!(date.date(), "2024-09-09T14:21:28");
+        assert_eq!(date.date(), Some("2024-09-09T14:21:28"));
     }
 
     #[test]
@@ -220,7 +991,7 @@ mod tests {
 
         assert_eq!(packages.len(), 5);
 
-        let package = fest.find_package("061561").unwrap();
+        let package = fest.find_package("061561").unwrap().unwrap();
         assert_eq!(package.itemnum(), "061561");
     }
 
@@ -231,7 +1002,7 @@ mod tests {
 
    //     assert_eq!(packages.len(), 10473);
 
-   //     let package = fest.find_package("061561").unwrap();
+   //     let package = fest.find_package("061561").unwrap().unwrap();
    //     assert_eq!(package.itemnum, "061561");
 
    //     let result = fest.find_generic(&package);
@@ -246,26 +1017,69 @@ mod tests {
 
         assert_eq!(packages.len(), 5);
 
-        let package = fest.find_package("061561").unwrap();
+        let package = fest.find_package("061561").unwrap().unwrap();
         assert_eq!(package.itemnum(), "061561");
 
-        let result = fest.find_generic(&package);
-        assert!(result.is_some());
-        assert_eq!(result.unwrap().len(), 4); // Should find 4 generics with same exchange group
+        let result = fest.find_generic(package).unwrap();
+        assert_eq!(result.count(), 4); // Should find 4 generics with same exchange group
+    }
+
+    #[test]
+    fn test_fest_cheapest_substitute() {
+        let fest = Fest::new("test_fest.xml").unwrap();
+        let package = fest.find_package("061561").unwrap().unwrap();
+        let ranked = |date| -> Vec<(String, f64, PriceBasis)> {
+            fest.cheapest_substitute(package, date)
+                .unwrap()
+                .map(|s| (s.package().itemnum().clone(), s.amount(), s.basis()))
+                .collect()
+        };
+
+        // 123456 and 953335 are cheaper, but smaller packs
+        assert_eq!(ranked(Timestamp::from_date(2023, 6, 1)), vec![
+            ("061561".to_string(), 130.0, PriceBasis::RetailPrice),
+        ]);
+        assert_eq!(ranked(Timestamp::from_date(2024, 9, 9)), vec![
+            ("061561".to_string(), 98.3, PriceBasis::StepPrice),
+        ]);
+        assert_eq!(ranked(Timestamp::from_date(2025, 6, 1)), vec![
+            ("654321".to_string(), 95.0, PriceBasis::StepPrice),
+            ("061561".to_string(), 98.3, PriceBasis::StepPrice),
+        ]);
+
+        let package = fest.find_package("017701").unwrap().unwrap();
+        assert_eq!(fest.cheapest_substitute(package, Timestamp::from_date(2025, 6, 1)).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_fest_find_generic_with() {
+        let fest = Fest::new("test_fest.xml").unwrap();
+        let package = fest.find_package("061561").unwrap().unwrap();
+
+        // marketed and in the group at the delivery date, 2024-09-09:
+        // 123456 is on the market but left the group at the end of 2023
+        let options = GenericOptions::new().only_marketed(true).include_self(false);
+        assert_eq!(fest.find_generic_with(package, &options).unwrap().count(), 0);
+
+        let options = options.as_of(Timestamp::from_date(2023, 6, 1));
+        let result: Vec<&String> = fest.find_generic_with(package, &options).unwrap().map(|p| p.itemnum()).collect();
+        assert_eq!(result, vec!["953335", "123456"]);
+
+        let package = fest.find_package("017701").unwrap().unwrap();
+        assert_eq!(fest.find_generic_with(package, &GenericOptions::default()).unwrap().count(), 0);
     }
 
     #[test]
     fn test_fest_find_interation() {
         let fest = Fest::new("test_fest.xml").unwrap();
 
-        let package1 = fest.find_package("061561").unwrap();
-        let package2 = fest.find_package("017701").unwrap();
+        let package1 = fest.find_package("061561").unwrap().unwrap();
+        let package2 = fest.find_package("017701").unwrap().unwrap();
 
-        let check_interaction = vec![package1, package2];
-        let interaction = fest.find_interaction(&check_interaction);
+        let check_interaction = [package1, package2];
+        let interaction = fest.find_interaction(&check_interaction).unwrap();
 
-        assert!(interaction.is_some());
-        assert_eq!(interaction.unwrap().len(), 1); // Our test file has 1 interaction
+        assert_eq!(interaction.count(), 1); // Our test file has 1 interaction
     }
 
 }
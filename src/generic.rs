@@ -0,0 +1,219 @@
+//! Options for finding the generic products of a package
+
+use crate::kith::Timestamp;
+use crate::types::Package;
+
+/// Options for [`Fest::find_generic_with`]
+///
+/// The default finds all the packages in the same exchange group,
+/// including the package itself, like [`Fest::find_generic`].
+///
+/// # Example
+/// ```
+/// use festlib::{Fest, GenericOptions, Timestamp};
+/// let fest = Fest::new("test_fest.xml").unwrap();
+/// let package = fest.find_package("061561").unwrap().unwrap();
+///
+/// let options = GenericOptions::new()
+///     .as_of(Timestamp::from_date(2024, 9, 9))
+///     .include_self(false)
+///     .only_marketed(true);
+///
+/// assert_eq!(fest.find_generic_with(package, &options).unwrap().count(), 0);
+/// ```
+///
+/// [`Fest::find_generic`]: crate::Fest::find_generic
+/// [`Fest::find_generic_with`]: crate::Fest::find_generic_with
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenericOptions {
+    as_of: Option<Timestamp>,
+    include_self: bool,
+    only_active: bool,
+    only_marketed: bool,
+    same_pack_size: bool,
+}
+
+impl Default for GenericOptions {
+    fn default() -> Self {
+        GenericOptions {
+            as_of: None,
+            include_self: true,
+            only_active: false,
+            only_marketed: false,
+            same_pack_size: false,
+        }
+    }
+}
+
+impl GenericOptions {
+    /// Options that find all the packages in the exchange group,
+    /// including the package itself
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The options for a substitution list at the date: other active
+    /// packages that are marketed and in the exchange group at the date
+    pub fn substitution(date: Timestamp) -> Self {
+        Self::new()
+            .as_of(date)
+            .include_self(false)
+            .only_active(true)
+            .only_marketed(true)
+    }
+
+    /// Only packages that are in the exchange group at the date
+    /// (GyldigFraDato and GyldigTilDato). The date is also used for
+    /// `only_marketed`.
+    pub fn as_of(mut self, date: Timestamp) -> Self {
+        self.as_of = Some(date);
+        self
+    }
+
+    /// Include the package itself, the default is true
+    pub fn include_self(mut self, include: bool) -> Self {
+        self.include_self = include;
+        self
+    }
+
+    /// Only packages with the status A (Aktiv)
+    pub fn only_active(mut self, only: bool) -> Self {
+        self.only_active = only;
+        self
+    }
+
+    /// Only packages on the market at the `as_of` date, or at the date
+    /// the fest file was made (HentetDato) if there is none. Without
+    /// `as_of`, the packages must also be in the exchange group at the
+    /// date the fest file was made.
+    pub fn only_marketed(mut self, only: bool) -> Self {
+        self.only_marketed = only;
+        self
+    }
+
+    /// Only packages with the same amount (Mengde) as the package.
+    /// Packages without an amount are not the same size.
+    pub fn same_pack_size(mut self, same: bool) -> Self {
+        self.same_pack_size = same;
+        self
+    }
+
+    /// Returns the date the options are for, if set
+    pub fn date(&self) -> Option<&Timestamp> {
+        self.as_of.as_ref()
+    }
+
+    /// Returns true if the candidate, from the same exchange group, is
+    /// a generic of the package. `today` is the date for
+    /// `only_marketed` when `as_of` isn't set.
+    pub(crate) fn matches(&self, package: &Package, candidate: &Package, today: Option<&Timestamp>) -> bool {
+        if !self.include_self && candidate.itemnum() == package.itemnum() {
+            return false;
+        }
+
+        // a package on the market today, but no longer in the group,
+        // can't be substituted
+        let date = match self.as_of.as_ref() {
+            Some(date) => Some(date),
+            None if self.only_marketed => today,
+            None => None,
+        };
+        let in_group = |p: &Package| {
+            date.is_none_or(|date| p.exchange_group().is_some_and(|g| g.is_valid(date)))
+        };
+        if !in_group(package) || !in_group(candidate) {
+            return false;
+        }
+
+        if self.only_active && candidate.metadata().status().and_then(|s| s.v()) != Some("A") {
+            return false;
+        }
+
+        if self.only_marketed {
+            match self.as_of.as_ref().or(today) {
+                Some(date) if candidate.is_marketed(date) => (),
+                _ => return false,
+            }
+        }
+
+        !self.same_pack_size
+            || matches!((package.quantity(), candidate.quantity()), (Some(a), Some(b)) if a == b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xml::test_packages;
+
+    fn generics<'a>(packages: &'a [Package], options: &GenericOptions, today: Option<&Timestamp>) -> Vec<&'a str> {
+        let package = &packages[0];
+        packages
+            .iter()
+            .filter(|p| p.exchange_id().is_some() && p.exchange_id() == package.exchange_id())
+            .filter(|p| options.matches(package, p, today))
+            .map(|p| p.itemnum().as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_default() {
+        let packages = test_packages();
+
+        let all = generics(&packages, &GenericOptions::default(), None);
+        assert_eq!(all, vec!["061561", "953335", "123456", "654321"]);
+
+        let others = generics(&packages, &GenericOptions::new().include_self(false), None);
+        assert_eq!(others, vec!["953335", "123456", "654321"]);
+    }
+
+    #[test]
+    fn test_as_of() {
+        let packages = test_packages();
+
+        // 123456 left the group at the end of 2023
+        let options = GenericOptions::new().as_of(Timestamp::from_date(2024, 9, 9));
+        assert_eq!(generics(&packages, &options, None), vec!["061561", "953335"]);
+
+        let options = GenericOptions::new().as_of(Timestamp::from_date(2019, 1, 1));
+        assert_eq!(generics(&packages, &options, None), vec!["061561", "953335", "123456"]);
+
+        // the package itself wasn't in the group yet
+        let options = GenericOptions::new().as_of(Timestamp::from_date(2011, 1, 1));
+        assert!(generics(&packages, &options, None).is_empty());
+    }
+
+    #[test]
+    fn test_only_marketed() {
+        let packages = test_packages();
+        let options = GenericOptions::new().only_marketed(true);
+
+        // 953335 is unavailable from 2024-08-01, and 123456 left the
+        // group at the end of 2023
+        let today = Timestamp::from_date(2024, 9, 9);
+        assert_eq!(generics(&packages, &options, Some(&today)), vec!["061561"]);
+
+        let today = Timestamp::from_date(2023, 6, 1);
+        assert_eq!(generics(&packages, &options, Some(&today)), vec!["061561", "953335", "123456"]);
+        assert!(generics(&packages, &options, None).is_empty());
+
+        let options = options.as_of(Timestamp::from_date(2025, 6, 1));
+        assert_eq!(generics(&packages, &options, Some(&today)), vec!["061561", "654321"]);
+    }
+
+    #[test]
+    fn test_same_pack_size() {
+        let packages = test_packages();
+        let options = GenericOptions::new().same_pack_size(true);
+
+        assert_eq!(generics(&packages, &options, None), vec!["061561", "654321"]);
+    }
+
+    #[test]
+    fn test_substitution() {
+        let packages = test_packages();
+        let options = GenericOptions::substitution(Timestamp::from_date(2025, 6, 1));
+
+        assert_eq!(generics(&packages, &options, None), vec!["654321"]);
+    }
+}
@@ -0,0 +1,186 @@
+//! Indexes over the parsed catalogs, so the finders don't scan all the
+//! packages on every call
+//!
+//! The indexes are built when the fest file is loaded, or on the first
+//! lookup for a fest that is deserialized. Every index keeps a copy of
+//! the key and the position of the entry, and the completions keep the
+//! names of the packages, substances and ATC groups twice, as written
+//! and normalized.
+//!
+//! The parse bench (`cargo bench`) measures the memory by counting the
+//! allocations: the indexes and completions together take 24.7 MB for
+//! its synthetic file of 50 000 packages, 10 000 interactions and
+//! 10 000 substances, or about 500 bytes per package. The memory grows
+//! with the number of entries and the length of the names.
+
+use std::collections::HashMap;
+use crate::complete::Completions;
+use crate::types::{ActiveSubstance, Interaction, Package, SubstanceStrength};
+
+/// Position of an entry (Oppf*) in one of the catalogs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EntryPosition {
+    Package(usize),
+    Interaction(usize),
+    ActiveSubstance(usize),
+    SubstanceStrength(usize),
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Index {
+    itemnum: HashMap<String, usize>,
+    ean: HashMap<String, usize>,
+    package_id: HashMap<String, usize>,
+    entry_id: HashMap<String, EntryPosition>,
+    exchange_group: HashMap<String, Vec<usize>>,
+    atc: HashMap<String, Vec<usize>>,
+    interaction_atc: HashMap<String, Vec<usize>>,
+    completions: Completions,
+}
+
+impl Index {
+    pub(crate) fn new(
+        packages: &[Package],
+        interactions: &[Interaction],
+        substances: &[ActiveSubstance],
+        substance_strengths: &[SubstanceStrength],
+    ) -> Self {
+        let mut index = Index {
+            completions: Completions::new(packages, substances, substance_strengths),
+            ..Index::default()
+        };
+
+        for (i, package) in packages.iter().enumerate() {
+            // the first package wins, as with a linear search
+            unique(&mut index.itemnum, package.itemnum(), i);
+            if let Some(ean) = package.ean() {
+                unique(&mut index.ean, ean, i);
+            }
+            unique(&mut index.package_id, package.id(), i);
+            unique(&mut index.entry_id, package.metadata().id(), EntryPosition::Package(i));
+
+            if let Some(id) = package.exchange_id() {
+                multiple(&mut index.exchange_group, id, i);
+            }
+            if let Some(atc) = package.atc().and_then(|a| a.v()) {
+                multiple(&mut index.atc, atc, i);
+            }
+        }
+
+        for (i, interaction) in interactions.iter().enumerate() {
+            unique(&mut index.entry_id, interaction.metadata().id(), EntryPosition::Interaction(i));
+            for atc in interaction.substances().iter().filter_map(|s| s.atc()) {
+                multiple(&mut index.interaction_atc, atc, i);
+            }
+        }
+
+        for (i, substance) in substances.iter().enumerate() {
+            unique(&mut index.entry_id, substance.metadata().id(), EntryPosition::ActiveSubstance(i));
+        }
+
+        for (i, strength) in substance_strengths.iter().enumerate() {
+            unique(&mut index.entry_id, strength.metadata().id(), EntryPosition::SubstanceStrength(i));
+        }
+
+        index
+    }
+
+    /// Position of the package with the itemnumber (varenr)
+    pub(crate) fn itemnum(&self, itemnum: &str) -> Option<usize> {
+        self.itemnum.get(itemnum).copied()
+    }
+
+    /// Position of the package with the EAN code
+    pub(crate) fn ean(&self, ean: &str) -> Option<usize> {
+        self.ean.get(ean).copied()
+    }
+
+    /// Position of the package with the id of <Legemiddelpakning>
+    pub(crate) fn package_id(&self, id: &str) -> Option<usize> {
+        self.package_id.get(id).copied()
+    }
+
+    /// Position of the entry with the Oppf id, in any of the catalogs
+    pub(crate) fn entry_id(&self, id: &str) -> Option<EntryPosition> {
+        self.entry_id.get(id).copied()
+    }
+
+    /// Positions of the packages in the exchange group, in file order
+    pub(crate) fn exchange_group(&self, id: &str) -> &[usize] {
+        self.exchange_group.get(id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Positions of the packages with the ATC code, in file order
+    pub(crate) fn atc(&self, atc: &str) -> &[usize] {
+        self.atc.get(atc).map_or(&[], Vec::as_slice)
+    }
+
+    /// Positions of the interactions with a substance with the ATC
+    /// code, in file order
+    pub(crate) fn interaction_atc(&self, atc: &str) -> &[usize] {
+        self.interaction_atc.get(atc).map_or(&[], Vec::as_slice)
+    }
+
+    /// Completions of names and ATC groups
+    pub(crate) fn completions(&self) -> &Completions {
+        &self.completions
+    }
+}
+
+fn unique<T>(map: &mut HashMap<String, T>, key: &str, position: T) {
+    if !key.is_empty() {
+        map.entry(key.to_string()).or_insert(position);
+    }
+}
+
+fn multiple(map: &mut HashMap<String, Vec<usize>>, key: &str, position: usize) {
+    let positions = map.entry(key.to_string()).or_default();
+
+    // an interaction can have the same ATC code in several substances
+    if positions.last() != Some(&position) {
+        positions.push(position);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xml;
+
+    fn index() -> (Vec<Package>, Index) {
+        let (packages, interactions, substances, strengths) = xml::test_catalogs();
+        let index = Index::new(&packages, &interactions, &substances, &strengths);
+
+        (packages, index)
+    }
+
+    #[test]
+    fn test_unique_keys() {
+        let (packages, index) = index();
+
+        let i = index.itemnum("061561").unwrap();
+        assert_eq!(packages[i].itemnum(), "061561");
+        assert_eq!(index.ean("7001234567890"), Some(i));
+        assert_eq!(index.package_id(packages[i].id()), Some(i));
+        assert_eq!(index.entry_id("ID_F994748F-3A21-4FC3-9964-DBE097924A75"), Some(EntryPosition::Package(i)));
+        assert_eq!(index.entry_id("ID_INT001-C908-43D8-AA07-9F8F00E6E7A3"), Some(EntryPosition::Interaction(0)));
+        assert_eq!(index.entry_id("ID_VIRK001-4FC3-9964-DBE097924A75"), Some(EntryPosition::ActiveSubstance(0)));
+        assert_eq!(index.entry_id("ID_VIRK002-4FC3-9964-DBE097924A75"), Some(EntryPosition::SubstanceStrength(0)));
+
+        assert_eq!(index.itemnum("000000"), None);
+        assert_eq!(index.ean(""), None);
+    }
+
+    #[test]
+    fn test_multiple_keys() {
+        let (packages, index) = index();
+
+        let group = index.exchange_group("BYTTE001");
+        assert_eq!(group.len(), 4);
+        assert!(group.windows(2).all(|w| w[0] < w[1]));
+        assert!(group.iter().all(|i| packages[*i].exchange_id().unwrap() == "BYTTE001"));
+
+        assert_eq!(index.interaction_atc("A01AA01"), &[0]);
+        assert!(index.atc("X99XX99").is_empty());
+    }
+}
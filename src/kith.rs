@@ -0,0 +1,1097 @@
+//! The KITH base datatypes (kith.xsd)
+//!
+//! All the attributes in kith.xsd are optional, so every value is kept
+//! as an Option. A value can also be null flavoured with the NULL
+//! attribute, which says why the value is missing.
+
+use std::cmp::Ordering;
+use std::fmt;
+use roxmltree::Node;
+use serde::{Deserialize, Serialize};
+use crate::unit;
+
+/// Parses a KITH datatype from the xml element
+pub(crate) trait Datatype: Sized {
+    fn parse(node: &Node) -> Self;
+}
+
+/// Finds the child element with the tag and parses it as the
+/// datatype. Returns None if the element is missing.
+pub(crate) fn element<T: Datatype>(node: &Node, tag: &str) -> Option<T> {
+    node.children()
+        .find(|n| n.has_tag_name(tag))
+        .map(|n| T::parse(&n))
+}
+
+/// Character string (ST)
+pub type St = String;
+
+/// Integer (INT)
+pub type Int = i64;
+
+/// Extracts a Character String (ST) from the text of the element
+pub(crate) fn st(node: &Node, tag: &str) -> Option<St> {
+    node.children()
+        .find(|n| n.has_tag_name(tag))
+        .map(|n| n.text().unwrap_or("").to_string())
+}
+
+/// Extracts an Integer (INT) from the text of the element.
+/// Returns None if the element is missing or isn't an integer.
+pub(crate) fn int(node: &Node, tag: &str) -> Option<Int> {
+    st(node, tag)?.trim().parse().ok()
+}
+
+/// Checks if the value is an object identifier (oid), e.g.
+/// 2.16.578.1.12.4.1.1.7180
+pub fn is_oid(value: &str) -> bool {
+    !value.is_empty()
+        && !value.ends_with('.')
+        && value.split('.').all(|p| !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit()))
+}
+
+fn attribute(node: &Node, name: &str) -> Option<String> {
+    node.attribute(name).map(String::from)
+}
+
+fn number(node: &Node, name: &str) -> Option<f64> {
+    node.attribute(name)?.trim().replace(',', ".").parse().ok()
+}
+
+fn null(node: &Node) -> Option<NullFlavor> {
+    node.attribute("NULL").and_then(NullFlavor::from_code)
+}
+
+/// Tells why a value is missing (NULL)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NullFlavor {
+    /// No information (NI)
+    NoInformation,
+    /// Not applicable (NA)
+    NotApplicable,
+    /// Unknown (UNK)
+    Unknown,
+    /// Not asked (NASK)
+    NotAsked,
+    /// Asked but unknown (ASKU)
+    AskedButUnknown,
+    /// Temporarily unavailable (NAV)
+    Unavailable,
+    /// Other (OTH)
+    Other,
+    /// Positive infinity (PINF)
+    PositiveInfinity,
+    /// Negative infinity (NINF)
+    NegativeInfinity,
+}
+
+impl NullFlavor {
+    pub fn from_code(code: &str) -> Option<Self> {
+        let flavor = match code {
+            "NI" => NullFlavor::NoInformation,
+            "NA" => NullFlavor::NotApplicable,
+            "UNK" => NullFlavor::Unknown,
+            "NASK" => NullFlavor::NotAsked,
+            "ASKU" => NullFlavor::AskedButUnknown,
+            "NAV" => NullFlavor::Unavailable,
+            "OTH" => NullFlavor::Other,
+            "PINF" => NullFlavor::PositiveInfinity,
+            "NINF" => NullFlavor::NegativeInfinity,
+            _ => return None,
+        };
+
+        Some(flavor)
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            NullFlavor::NoInformation => "NI",
+            NullFlavor::NotApplicable => "NA",
+            NullFlavor::Unknown => "UNK",
+            NullFlavor::NotAsked => "NASK",
+            NullFlavor::AskedButUnknown => "ASKU",
+            NullFlavor::Unavailable => "NAV",
+            NullFlavor::Other => "OTH",
+            NullFlavor::PositiveInfinity => "PINF",
+            NullFlavor::NegativeInfinity => "NINF",
+        }
+    }
+}
+
+/// Boolean (BL)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bl {
+    v: Option<bool>,
+    null: Option<NullFlavor>,
+}
+
+impl Datatype for Bl {
+    fn parse(node: &Node) -> Self {
+        let v = match node.attribute("V") {
+            Some("true") => Some(true),
+            Some("false") => Some(false),
+            _ => None,
+        };
+
+        Bl {
+            v,
+            null: null(node),
+        }
+    }
+}
+
+impl Bl {
+    pub fn from(v: bool) -> Self {
+        Bl {
+            v: Some(v),
+            null: None,
+        }
+    }
+
+    pub fn v(&self) -> Option<bool> {
+        self.v
+    }
+
+    pub fn null(&self) -> Option<NullFlavor> {
+        self.null
+    }
+}
+
+/// Real number (REAL)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Real {
+    v: Option<f64>,
+    null: Option<NullFlavor>,
+}
+
+impl Datatype for Real {
+    fn parse(node: &Node) -> Self {
+        Real {
+            v: number(node, "V"),
+            null: null(node),
+        }
+    }
+}
+
+impl Real {
+    pub fn from(v: f64) -> Self {
+        Real {
+            v: Some(v),
+            null: None,
+        }
+    }
+
+    pub fn v(&self) -> Option<f64> {
+        self.v
+    }
+
+    pub fn null(&self) -> Option<NullFlavor> {
+        self.null
+    }
+}
+
+/// Time of day, part of a Timestamp
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Time {
+    hour: u8,
+    minute: u8,
+    second: u8,
+}
+
+/// A point in time as given in the xml, from a year down to a second,
+/// or only a time of day. Parts that are not given (a date without
+/// time, a year without month) are None, and sorts before the values
+/// that have them. Time zones are ignored since FEST uses Norwegian
+/// local time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Timestamp {
+    year: Option<i32>,
+    month: Option<u8>,
+    day: Option<u8>,
+    time: Option<Time>,
+}
+
+impl Timestamp {
+    /// Parses a xml dateTime, date, gYearMonth, gYear or time, the
+    /// members of TS in kith.xsd
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::Timestamp;
+    ///
+    /// let ts = Timestamp::parse("2024-09-09T14:21:28").unwrap();
+    /// assert_eq!(ts.date(), Some((2024, 9, 9)));
+    /// assert_eq!(ts.to_string(), "2024-09-09T14:21:28");
+    ///
+    /// let time = Timestamp::parse("14:21:28").unwrap();
+    /// assert_eq!((time.date(), time.time()), (None, Some((14, 21, 28))));
+    /// ```
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+
+        // a time without a date
+        if value.get(2..3) == Some(":") {
+            return Some(Timestamp {
+                year: None,
+                month: None,
+                day: None,
+                time: Some(Timestamp::time_of_day(value)?),
+            });
+        }
+
+        let (date, time) = match value.split_once('T') {
+            Some((date, time)) => (date, Some(time)),
+            None => (value, None),
+        };
+
+        let date = Timestamp::without_zone(date);
+
+        let mut parts = date.splitn(3, '-');
+        let year = parts.next()?;
+        if year.len() != 4 {
+            return None;
+        }
+        let year = year.parse().ok()?;
+        let month = match parts.next() {
+            Some(m) => Some(Timestamp::field(m, 1, 12)?),
+            None => None,
+        };
+        let day = match (month, parts.next()) {
+            (Some(m), Some(d)) => Some(Timestamp::field(d, 1, days_in_month(year, m))?),
+            _ => None,
+        };
+
+        let time = match time {
+            Some(time) => {
+                // a time needs a full date
+                day?;
+                Some(Timestamp::time_of_day(time)?)
+            }
+            None => None,
+        };
+
+        Some(Timestamp {
+            year: Some(year),
+            month,
+            day,
+            time,
+        })
+    }
+
+    /// Creates a timestamp for the start of a date
+    ///
+    /// # Panics
+    /// If the date doesn't exist, e.g. month 13 or 31 February
+    pub fn from_date(year: i32, month: u8, day: u8) -> Self {
+        let valid = (1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day);
+        assert!(valid, "invalid date {:04}-{:02}-{:02}", year, month, day);
+
+        Timestamp {
+            year: Some(year),
+            month: Some(month),
+            day: Some(day),
+            time: None,
+        }
+    }
+
+    // parses hh:mm:ss, without fractions and time zone
+    fn time_of_day(time: &str) -> Option<Time> {
+        let time = time
+            .split(['.', 'Z', '+', '-'])
+            .next()?;
+        let mut parts = time.split(':');
+        let hour = Timestamp::field(parts.next()?, 0, 23)?;
+        let minute = Timestamp::field(parts.next()?, 0, 59)?;
+        let second = Timestamp::field(parts.next().unwrap_or("00"), 0, 60)?;
+
+        Some(Time { hour, minute, second })
+    }
+
+    // removes the time zone from a date, gYearMonth or gYear: Z, +hh:mm
+    // or -hh:mm
+    fn without_zone(date: &str) -> &str {
+        if let Some(i) = date.find(['+', 'Z']) {
+            return &date[..i];
+        }
+
+        let bytes = date.as_bytes();
+        match bytes.len().checked_sub(6) {
+            Some(i) if i > 0 && bytes[i] == b'-' && bytes[i + 3] == b':' => &date[..i],
+            _ => date,
+        }
+    }
+
+    fn field(value: &str, min: u8, max: u8) -> Option<u8> {
+        if value.len() != 2 {
+            return None;
+        }
+
+        value.parse().ok().filter(|v| (min..=max).contains(v))
+    }
+
+    /// Returns the year, month and day. Missing month
+    /// and day is given as 1. None for a time without a date.
+    pub fn date(&self) -> Option<(i32, u8, u8)> {
+        Some((self.year?, self.month.unwrap_or(1), self.day.unwrap_or(1)))
+    }
+
+    /// Returns the hour, minute and second if the timestamp has a time
+    pub fn time(&self) -> Option<(u8, u8, u8)> {
+        self.time.map(|t| (t.hour, t.minute, t.second))
+    }
+
+    /// Checks if the timestamp is a full date without time (xs:date)
+    pub(crate) fn is_date(&self) -> bool {
+        self.day.is_some() && self.time.is_none()
+    }
+
+    /// Checks if the timestamp is a full date with time (xs:dateTime)
+    pub(crate) fn is_date_time(&self) -> bool {
+        self.day.is_some() && self.time.is_some()
+    }
+
+    /// Returns the timestamp without the time, to compare
+    /// against dates.
+    pub fn day(&self) -> Timestamp {
+        Timestamp {
+            time: None,
+            ..*self
+        }
+    }
+}
+
+// the number of days in the month of the year
+fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(year) = self.year {
+            write!(f, "{:04}", year)?;
+        }
+
+        if let Some(month) = self.month {
+            write!(f, "-{:02}", month)?;
+        }
+        if let Some(day) = self.day {
+            write!(f, "-{:02}", day)?;
+        }
+        if let Some(t) = self.time {
+            if self.year.is_some() {
+                write!(f, "T")?;
+            }
+            write!(f, "{:02}:{:02}:{:02}", t.hour, t.minute, t.second)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Point in time (TS)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ts {
+    v: Option<Timestamp>,
+    null: Option<NullFlavor>,
+}
+
+impl Datatype for Ts {
+    fn parse(node: &Node) -> Self {
+        Ts {
+            v: node.attribute("V").and_then(Timestamp::parse),
+            null: null(node),
+        }
+    }
+}
+
+impl Ts {
+    pub fn from(v: Timestamp) -> Self {
+        Ts {
+            v: Some(v),
+            null: None,
+        }
+    }
+
+    pub fn v(&self) -> Option<&Timestamp> {
+        self.v.as_ref()
+    }
+
+    pub fn null(&self) -> Option<NullFlavor> {
+        self.null
+    }
+}
+
+/// Uniform resource locator (URL)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Url {
+    v: Option<String>,
+    null: Option<NullFlavor>,
+}
+
+impl Datatype for Url {
+    fn parse(node: &Node) -> Self {
+        Url {
+            v: attribute(node, "V"),
+            null: null(node),
+        }
+    }
+}
+
+impl Url {
+    pub fn v(&self) -> Option<&str> {
+        self.v.as_deref()
+    }
+
+    pub fn null(&self) -> Option<NullFlavor> {
+        self.null
+    }
+}
+
+/// Encapsulated data (ED)
+/// Text with an optional media type (MT)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ed {
+    text: Option<String>,
+    mt: Option<String>,
+    null: Option<NullFlavor>,
+}
+
+impl Datatype for Ed {
+    fn parse(node: &Node) -> Self {
+        let text: String = node
+            .children()
+            .filter(|n| n.is_text())
+            .filter_map(|n| n.text())
+            .collect();
+
+        Ed {
+            text: Some(text).filter(|t| !t.trim().is_empty()),
+            mt: attribute(node, "MT"),
+            null: null(node),
+        }
+    }
+}
+
+impl Ed {
+    pub fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+
+    pub fn mt(&self) -> Option<&str> {
+        self.mt.as_deref()
+    }
+
+    pub fn null(&self) -> Option<NullFlavor> {
+        self.null
+    }
+}
+
+/// Text (TN)
+/// Encapsulated data with an optional reference (REF) and
+/// compression (COMPN)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tn {
+    ed: Ed,
+    reference: Option<Url>,
+    compn: Option<String>,
+}
+
+impl Datatype for Tn {
+    fn parse(node: &Node) -> Self {
+        Tn {
+            ed: Ed::parse(node),
+            reference: element(node, "REF"),
+            compn: attribute(node, "COMPN"),
+        }
+    }
+}
+
+impl Tn {
+    pub fn text(&self) -> Option<&str> {
+        self.ed.text()
+    }
+
+    pub fn reference(&self) -> Option<&Url> {
+        self.reference.as_ref()
+    }
+
+    pub fn compn(&self) -> Option<&str> {
+        self.compn.as_deref()
+    }
+
+    pub fn null(&self) -> Option<NullFlavor> {
+        self.ed.null()
+    }
+}
+
+/// Coded Simple Value (CS)
+/// Gives a codes value with a String with an option
+/// to give the 'v' a meaning 'dn'
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cs {
+    v: Option<String>,
+    dn: Option<String>,
+    null: Option<NullFlavor>,
+}
+
+impl Datatype for Cs {
+    fn parse(node: &Node) -> Self {
+        Cs {
+            v: attribute(node, "V"),
+            dn: attribute(node, "DN"),
+            null: null(node),
+        }
+    }
+}
+
+impl Cs {
+    pub fn new(node: &Node, tag: &str) -> Option<Self> {
+        element(node, tag)
+    }
+
+    pub fn from(v: &str, dn: &str) -> Self {
+        Cs {
+            v: Some(String::from(v)),
+            dn: Some(String::from(dn)),
+            null: None,
+        }
+    }
+
+    pub fn v(&self) -> Option<&str> {
+        self.v.as_deref()
+    }
+
+    pub fn dn(&self) -> Option<&str> {
+        self.dn.as_deref()
+    }
+
+    pub fn null(&self) -> Option<NullFlavor> {
+        self.null
+    }
+}
+
+/// Coded Value with a OID (object identifier) (CV)
+/// s = oid.
+/// the oid have a constant value but the last part
+/// is the identifier
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cv {
+    v: Option<String>,
+    s: Option<String>,
+    dn: Option<String>,
+    ot: Option<String>,
+    null: Option<NullFlavor>,
+}
+
+impl Datatype for Cv {
+    fn parse(node: &Node) -> Self {
+        Cv {
+            v: attribute(node, "V"),
+            s: attribute(node, "S"),
+            dn: attribute(node, "DN"),
+            ot: attribute(node, "OT"),
+            null: null(node),
+        }
+    }
+}
+
+impl Cv {
+    pub fn new(node: &Node, tag: &str) -> Option<Self> {
+        element(node, tag)
+    }
+
+    pub fn from(v: &str, s: &str, dn: &str) -> Self {
+        Cv {
+            v: Some(String::from(v)),
+            s: Some(String::from(s)),
+            dn: Some(String::from(dn)),
+            ot: None,
+            null: None,
+        }
+    }
+
+    pub fn v(&self) -> Option<&str> {
+        self.v.as_deref()
+    }
+
+    pub fn s(&self) -> Option<&str> {
+        self.s.as_deref()
+    }
+
+    pub fn dn(&self) -> Option<&str> {
+        self.dn.as_deref()
+    }
+
+    /// Original text (OT)
+    pub fn ot(&self) -> Option<&str> {
+        self.ot.as_deref()
+    }
+
+    pub fn null(&self) -> Option<NullFlavor> {
+        self.null
+    }
+}
+
+/// Physical Quantity (PQ)
+/// A value with a unit, e.g. the amount in a package
+/// or the strength of a substance. The unit is used
+/// when comparing, so 1 g is equal to 1000 mg.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pq {
+    v: Option<f64>,
+    u: Option<String>,
+    null: Option<NullFlavor>,
+}
+
+impl Datatype for Pq {
+    fn parse(node: &Node) -> Self {
+        Pq {
+            v: number(node, "V"),
+            u: attribute(node, "U"),
+            null: null(node),
+        }
+    }
+}
+
+impl Pq {
+    pub fn new(node: &Node, tag: &str) -> Option<Self> {
+        element(node, tag)
+    }
+
+    pub fn from(v: f64, u: &str) -> Self {
+        Pq {
+            v: Some(v),
+            u: Some(String::from(u)).filter(|u| !u.is_empty()),
+            null: None,
+        }
+    }
+
+    pub fn v(&self) -> Option<f64> {
+        self.v
+    }
+
+    pub fn u(&self) -> Option<&str> {
+        self.u.as_deref()
+    }
+
+    pub fn null(&self) -> Option<NullFlavor> {
+        self.null
+    }
+
+    /// Converts the quantity to another unit, e.g. g to mg.
+    /// Returns None if there is no value or the units can't
+    /// be converted.
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::Pq;
+    ///
+    /// let pq = Pq::from(0.5, "g");
+    /// assert_eq!(pq.convert_to("mg").unwrap().v(), Some(500.0));
+    /// ```
+    pub fn convert_to(&self, unit: &str) -> Option<Pq> {
+        let v = unit::convert(self.v?, self.u().unwrap_or(""), unit)?;
+
+        Some(Pq::from(v, unit))
+    }
+}
+
+impl PartialEq for Pq {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Pq {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        let value = self.v?;
+        let other = other.convert_to(self.u().unwrap_or(""))?.v?;
+
+        if unit::approx_eq(value, other) {
+            Some(Ordering::Equal)
+        } else {
+            value.partial_cmp(&other)
+        }
+    }
+}
+
+impl fmt::Display for Pq {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.v, self.u(), self.null) {
+            (Some(v), Some(u), _) => write!(f, "{} {}", v, u),
+            (Some(v), None, _) => write!(f, "{}", v),
+            (None, _, Some(null)) => write!(f, "{}", null.code()),
+            (None, _, None) => Ok(()),
+        }
+    }
+}
+
+/// Monetary amount (MO)
+/// u is the currency, e.g. NOK
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Mo {
+    v: Option<f64>,
+    u: Option<String>,
+    null: Option<NullFlavor>,
+}
+
+impl Datatype for Mo {
+    fn parse(node: &Node) -> Self {
+        Mo {
+            v: number(node, "V"),
+            u: attribute(node, "U"),
+            null: null(node),
+        }
+    }
+}
+
+impl Mo {
+    pub fn new(node: &Node, tag: &str) -> Option<Self> {
+        element(node, tag)
+    }
+
+    pub fn from(v: f64, u: &str) -> Self {
+        Mo {
+            v: Some(v),
+            u: Some(String::from(u)),
+            null: None,
+        }
+    }
+
+    pub fn v(&self) -> Option<f64> {
+        self.v
+    }
+
+    pub fn u(&self) -> Option<&str> {
+        self.u.as_deref()
+    }
+
+    pub fn null(&self) -> Option<NullFlavor> {
+        self.null
+    }
+}
+
+/// Ratio between two quantities (RTO)
+/// Is used for strengths, where num is the amount of
+/// the substance and denom is what its given per, e.g. 10 mg/5 ml.
+/// Strengths without a denominator (a 500 mg tablet) have
+/// a denom of 1 without unit.
+/// The RTO in kith.xsd has the integer elements NUM and DENOM,
+/// these are read as quantities without unit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rto {
+    num: Pq,
+    denom: Pq,
+}
+
+impl Datatype for Rto {
+    fn parse(node: &Node) -> Self {
+        let value = |tag| int(node, tag).map(|v| Pq::from(v as f64, ""));
+
+        Rto {
+            num: value("NUM").unwrap_or_else(Rto::empty),
+            denom: value("DENOM").unwrap_or_else(|| Pq::from(1.0, "")),
+        }
+    }
+}
+
+impl Rto {
+    /// Reads a ratio from two PQ elements in the node, like
+    /// Styrke and StyrkeNevner. Returns None if num is missing.
+    pub fn new(node: &Node, num: &str, denom: &str) -> Option<Self> {
+        let num = Pq::new(node, num)?;
+        let denom = Pq::new(node, denom).unwrap_or_else(|| Pq::from(1.0, ""));
+
+        Some(Rto {
+            num,
+            denom,
+        })
+    }
+
+    pub fn from(num: Pq, denom: Pq) -> Self {
+        Rto {
+            num,
+            denom,
+        }
+    }
+
+    fn empty() -> Pq {
+        Pq {
+            v: None,
+            u: None,
+            null: None,
+        }
+    }
+
+    pub fn num(&self) -> &Pq {
+        &self.num
+    }
+
+    pub fn denom(&self) -> &Pq {
+        &self.denom
+    }
+
+    /// The value of the ratio, num divided by denom
+    pub fn value(&self) -> Option<f64> {
+        Some(self.num.v? / self.denom.v?)
+    }
+
+    /// Converts the ratio to other units, e.g. g/l to mg/ml.
+    /// The result is given per one denominator unit.
+    /// Returns None if any of the units can't be converted.
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::{Pq, Rto};
+    ///
+    /// let rto = Rto::from(Pq::from(10.0, "mg"), Pq::from(5.0, "ml"));
+    /// let per_l = rto.convert_to("g", "l").unwrap();
+    ///
+    /// assert_eq!(per_l.value(), Some(2.0));
+    /// ```
+    pub fn convert_to(&self, num: &str, denom: &str) -> Option<Rto> {
+        let n = self.num.convert_to(num)?.v?;
+        let d = self.denom.convert_to(denom)?.v?;
+
+        Some(Rto::from(Pq::from(n / d, num), Pq::from(1.0, denom)))
+    }
+}
+
+impl PartialEq for Rto {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Rto {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        let other = other
+            .convert_to(self.num.u().unwrap_or(""), self.denom.u().unwrap_or(""))?
+            .value()?;
+        let value = self.value()?;
+
+        if unit::approx_eq(value, other) {
+            Some(Ordering::Equal)
+        } else {
+            value.partial_cmp(&other)
+        }
+    }
+}
+
+impl fmt::Display for Rto {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.denom.v, self.denom.u()) {
+            (Some(1.0), None) => write!(f, "{}", self.num),
+            (Some(1.0), Some(u)) => write!(f, "{}/{}", self.num, u),
+            _ => write!(f, "{}/{}", self.num, self.denom),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse<T: Datatype>(xml: &str) -> T {
+        let document = roxmltree::Document::parse(xml).unwrap();
+        element(&document.root_element(), "X").unwrap()
+    }
+
+    #[test]
+    fn test_cs() {
+        let cs: Cs = parse(r#"<R><X V="A" DN="Aktiv"/></R>"#);
+        assert_eq!(cs.v(), Some("A"));
+        assert_eq!(cs.dn(), Some("Aktiv"));
+
+        let cs: Cs = parse(r#"<R><X NULL="UNK"/></R>"#);
+        assert_eq!(cs.v(), None);
+        assert_eq!(cs.null(), Some(NullFlavor::Unknown));
+    }
+
+    #[test]
+    fn test_cv() {
+        let cv: Cv = parse(r#"<R><X V="32" S="2.16.578.1.12.4.1.1.7448" DN="Kapsel" OT="kaps"/></R>"#);
+        assert_eq!(cv.v(), Some("32"));
+        assert_eq!(cv.s(), Some("2.16.578.1.12.4.1.1.7448"));
+        assert_eq!(cv.dn(), Some("Kapsel"));
+        assert_eq!(cv.ot(), Some("kaps"));
+    }
+
+    #[test]
+    fn test_missing_element() {
+        let document = roxmltree::Document::parse("<R/>").unwrap();
+        let cs: Option<Cs> = element(&document.root_element(), "X");
+        assert!(cs.is_none());
+    }
+
+    #[test]
+    fn test_bl_real_url_mo() {
+        let bl: Bl = parse(r#"<R><X V="true"/></R>"#);
+        assert_eq!(bl.v(), Some(true));
+
+        let real: Real = parse(r#"<R><X V="1.5"/></R>"#);
+        assert_eq!(real.v(), Some(1.5));
+
+        let url: Url = parse(r#"<R><X V="https://www.dmp.no"/></R>"#);
+        assert_eq!(url.v(), Some("https://www.dmp.no"));
+
+        let mo: Mo = parse(r#"<R><X V="123.45" U="NOK"/></R>"#);
+        assert_eq!(mo.v(), Some(123.45));
+        assert_eq!(mo.u(), Some("NOK"));
+
+        let mo: Mo = parse(r#"<R><X V="abc"/></R>"#);
+        assert_eq!(mo.v(), None);
+    }
+
+    #[test]
+    fn test_st_int() {
+        let document = roxmltree::Document::parse("<R><A>text</A><B>42</B><C/></R>").unwrap();
+        let root = document.root_element();
+
+        assert_eq!(st(&root, "A").as_deref(), Some("text"));
+        assert_eq!(st(&root, "C").as_deref(), Some(""));
+        assert_eq!(st(&root, "D"), None);
+        assert_eq!(int(&root, "B"), Some(42));
+        assert_eq!(int(&root, "A"), None);
+    }
+
+    #[test]
+    fn test_ed_tn() {
+        let ed: Ed = parse(r#"<R><X MT="text/plain">Some text</X></R>"#);
+        assert_eq!(ed.text(), Some("Some text"));
+        assert_eq!(ed.mt(), Some("text/plain"));
+
+        let tn: Tn = parse(r#"<R><X NULL="NAV"><REF V="https://www.dmp.no"/></X></R>"#);
+        assert_eq!(tn.text(), None);
+        assert_eq!(tn.reference().unwrap().v(), Some("https://www.dmp.no"));
+        assert_eq!(tn.null(), Some(NullFlavor::Unavailable));
+    }
+
+    #[test]
+    fn test_ts() {
+        let ts: Ts = parse(r#"<R><X V="2024-09-09T14:21:28"/></R>"#);
+        let v = ts.v().unwrap();
+        assert_eq!(v.date(), Some((2024, 9, 9)));
+        assert_eq!(v.time(), Some((14, 21, 28)));
+
+        let ts: Ts = parse(r#"<R><X V="2024-13-01"/></R>"#);
+        assert!(ts.v().is_none());
+    }
+
+    #[test]
+    fn test_timestamp() {
+        let year = Timestamp::parse("2024").unwrap();
+        let date = Timestamp::parse("2024-04-21").unwrap();
+        let time = Timestamp::parse("2024-04-21T00:51:31.123+02:00").unwrap();
+
+        assert_eq!(year.to_string(), "2024");
+        assert_eq!(time.to_string(), "2024-04-21T00:51:31");
+        assert_eq!(time.day(), date);
+        assert!(year < date);
+        assert!(date < time);
+        assert_eq!(Timestamp::from_date(2024, 4, 21), date);
+        assert_eq!(Timestamp::parse("2024-04-21+02:00"), Some(date));
+        assert!(Timestamp::parse("21.04.2024").is_none());
+    }
+
+    #[test]
+    fn test_timestamp_zone() {
+        let month = Timestamp::parse("2024-04").unwrap();
+
+        assert_eq!(Timestamp::parse("2024-04+02:00"), Some(month));
+        assert_eq!(Timestamp::parse("2024-04-05:00"), Some(month));
+        assert_eq!(Timestamp::parse("2024-04Z"), Some(month));
+        assert_eq!(Timestamp::parse("2024-04-21-05:00"), Timestamp::parse("2024-04-21"));
+        assert_eq!(Timestamp::parse("2024Z"), Timestamp::parse("2024"));
+        assert_eq!(Timestamp::parse("2024-05:00"), Timestamp::parse("2024"));
+    }
+
+    #[test]
+    fn test_timestamp_day() {
+        assert!(Timestamp::parse("2024-02-31").is_none());
+        assert!(Timestamp::parse("2023-02-29").is_none());
+        assert!(Timestamp::parse("2024-04-31").is_none());
+        assert_eq!(Timestamp::parse("2024-02-29").unwrap().date(), Some((2024, 2, 29)));
+        assert_eq!(Timestamp::parse("2000-02-29").unwrap().date(), Some((2000, 2, 29)));
+        assert!(Timestamp::parse("1900-02-29").is_none());
+    }
+
+    #[test]
+    fn test_timestamp_time() {
+        let time = Timestamp::parse("14:21:28").unwrap();
+
+        assert_eq!(time.date(), None);
+        assert_eq!(time.time(), Some((14, 21, 28)));
+        assert_eq!(time.to_string(), "14:21:28");
+        assert_eq!(Timestamp::parse("14:21:28.5+02:00"), Some(time));
+        assert!(time < Timestamp::parse("2024").unwrap());
+        assert!(Timestamp::parse("24:00:00").is_none());
+        assert!(Timestamp::parse("14:21:28T10:00:00").is_none());
+    }
+
+    #[test]
+    fn test_from_date() {
+        assert_eq!(Timestamp::from_date(2024, 2, 29), Timestamp::parse("2024-02-29").unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid date 2024-02-31")]
+    fn test_from_date_invalid_day() {
+        Timestamp::from_date(2024, 2, 31);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid date 2024-13-01")]
+    fn test_from_date_invalid_month() {
+        Timestamp::from_date(2024, 13, 1);
+    }
+
+    #[test]
+    fn test_rto() {
+        let rto: Rto = parse(r#"<R><X><NUM>1</NUM><DENOM>4</DENOM></X></R>"#);
+        assert_eq!(rto.value(), Some(0.25));
+        assert_eq!(rto.to_string(), "1/4");
+    }
+
+    #[test]
+    fn test_oid() {
+        assert!(is_oid("2.16.578.1.12.4.1.1.7180"));
+        assert!(is_oid("7180"));
+        assert!(!is_oid("2.16.."));
+        assert!(!is_oid("2.16.a"));
+        assert!(!is_oid(""));
+    }
+
+    #[test]
+    fn test_null_flavor() {
+        assert_eq!(NullFlavor::from_code("ASKU"), Some(NullFlavor::AskedButUnknown));
+        assert_eq!(NullFlavor::Other.code(), "OTH");
+        assert_eq!(NullFlavor::from_code("XYZ"), None);
+    }
+
+    #[test]
+    fn test_pq_compare() {
+        assert_eq!(Pq::from(1.0, "g"), Pq::from(1000.0, "mg"));
+        assert!(Pq::from(500.0, "mikrog") < Pq::from(1.0, "mg"));
+        assert!(Pq::from(1.0, "g").partial_cmp(&Pq::from(1.0, "ml")).is_none());
+        assert_ne!(Pq::from(1.0, "IE"), Pq::from(1.0, "stk"));
+    }
+
+    #[test]
+    fn test_rto_compare() {
+        let a = Rto::from(Pq::from(10.0, "mg"), Pq::from(5.0, "ml"));
+        let b = Rto::from(Pq::from(2.0, "g"), Pq::from(1.0, "l"));
+        let c = Rto::from(Pq::from(500.0, "mg"), Pq::from(1.0, ""));
+
+        assert_eq!(a, b);
+        assert_eq!(a.to_string(), "10 mg/5 ml");
+        assert_eq!(a.convert_to("mg", "ml").unwrap().to_string(), "2 mg/ml");
+        assert_eq!(c.to_string(), "500 mg");
+        assert!(a.partial_cmp(&c).is_none());
+    }
+}
@@ -0,0 +1,66 @@
+//! Parser backend for M30 v2.5 (2013-10-08)
+//!
+//! v2.5 has the same catalogs and entries as the current version, but
+//! the amount in the package (Mengde) is a decimal number with the unit
+//! in <EnhetPakning>, and not a PQ.
+
+use roxmltree::{Document, Node};
+use crate::kith::{self, Cv, Pq};
+use crate::report::ParseReport;
+use crate::types::Package;
+use crate::xml;
+
+/// Retrives the xml data from <OppfLegemiddelpakning>
+pub(crate) fn package(node: &Node) -> Option<Package> {
+    xml::package_with(node, quantity)
+}
+
+/// Retrieves all the packages (OppfLegemiddelpakning) from the xml file
+pub(crate) fn packages(document: &Document, report: &mut ParseReport) -> Vec<Package> {
+    xml::packages_with(document, package, report)
+}
+
+// <Mengde>28</Mengde> with the unit from <EnhetPakning V="stk"/>. Files
+// that already use the PQ are read as PQ.
+fn quantity(info: &Node) -> Option<Pq> {
+    let mengde = info.children().find(|n| n.has_tag_name("Mengde"))?;
+    if mengde.has_attribute("V") {
+        return Pq::new(info, "Mengde");
+    }
+
+    let value = kith::st(info, "Mengde")?.trim().replace(',', ".").parse().ok()?;
+    let unit = Cv::new(info, "EnhetPakning");
+
+    Some(Pq::from(value, unit.as_ref().and_then(|u| u.v()).unwrap_or("")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_packages() {
+        let content = fs::read_to_string("test_fest_v25.xml").unwrap();
+        let document = xml::document(&content).unwrap();
+
+        let packages = packages(&document, &mut Default::default());
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].itemnum(), "061561");
+        assert_eq!(packages[0].atc().unwrap().v(), Some("A01AA01"));
+        assert_eq!(packages[0].exchange_id().unwrap(), "BYTTE001");
+        assert_eq!(packages[0].quantity().unwrap().to_string(), "28 stk");
+        assert_eq!(packages[0].ddd().unwrap().to_string(), "20 mg");
+        assert_eq!(packages[1].quantity().unwrap().to_string(), "100.5 ml");
+    }
+
+    #[test]
+    fn test_quantity_pq() {
+        let document = xml::document(r#"<Pakningsinfo><Mengde V="28" U="stk"/></Pakningsinfo>"#).unwrap();
+        let quantity = quantity(&document.root_element()).unwrap();
+        assert_eq!(quantity.to_string(), "28 stk");
+
+        let document = xml::document("<Pakningsinfo><Mengde>mange</Mengde></Pakningsinfo>").unwrap();
+        assert!(super::quantity(&document.root_element()).is_none());
+    }
+}
@@ -14,7 +14,7 @@
 //! let fest = Fest::new("test_fest.xml").unwrap();
 //! let date = fest.delivery_date();
 //!
-//! assert_eq!(date.date(), "2024-09-09T14:21:28");
+//! assert_eq!(date.date(), Some("2024-09-09T14:21:28"));
 //! ```
 //!
 //! ### Find packages
@@ -31,7 +31,7 @@
 //! ```
 //! use festlib::Fest;
 //! let fest = Fest::new("test_fest.xml").unwrap();
-//! let package = fest.find_package("061561").unwrap();
+//! let package = fest.find_package("061561").unwrap().unwrap();
 //!
 //! let result = fest.find_generic(&package);
 //! ```
@@ -39,9 +39,51 @@
 //! For questions or feedback use make a issue on our github or john.doe.hemmelig@pm.me.
 //!
 
+mod archive;
+mod atc;
+mod builder;
+mod catalog;
+mod complete;
+mod error;
 mod fest;
+mod generic;
+mod index;
+mod legacy;
+mod lookup;
+mod query;
+mod release;
+mod report;
+mod schema;
+mod search;
+mod snapshot;
+mod stats;
+mod substitute;
 mod xml;
 mod types;
+mod stream;
+mod unit;
+mod validate;
+mod view;
+pub mod kith;
 
-pub use crate::fest::Fest;
-pub use crate::types::Package;
+pub use crate::error::FestError;
+pub use crate::builder::FestBuilder;
+pub use crate::atc::{atc_level, AtcNode, AtcTree};
+pub use crate::catalog::Catalog;
+pub use crate::complete::{Completion, CompletionKind};
+pub use crate::fest::{Fest, LoadOptions};
+pub use crate::generic::GenericOptions;
+pub use crate::schema::SchemaVersion;
+pub use crate::snapshot::SnapshotInfo;
+pub use crate::stats::Count;
+pub use crate::release::ReleaseInfo;
+pub use crate::report::{ParseReport, Warning, WarningKind};
+pub use crate::validate::{Violation, ViolationKind};
+pub use crate::stream::{Entry, FestReader};
+pub use crate::lookup::{EntryRef, Identifier};
+pub use crate::query::{Order, PackageQuery};
+pub use crate::search::SearchResult;
+pub use crate::substitute::{PriceBasis, Substitute};
+pub use crate::kith::{Cs, Cv, Mo, Pq, Rto, Timestamp};
+pub use crate::view::{CodeRef, FestView, InteractionRef, PackageRef, SubstanceRef};
+pub use crate::types::{ActiveSubstance, ExchangeGroup, Interaction, LastUpdate, MarketingInfo, Metadata, Package, Price, Substance, SubstanceStrength};
@@ -0,0 +1,86 @@
+use std::borrow::Cow;
+use crate::types::{ActiveSubstance, Interaction, Package, SubstanceStrength};
+
+/// The kind of identifier for a package
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Identifier {
+    /// Itemnumber (varenr), six digits
+    Itemnum,
+    /// EAN/GTIN barcode, 8, 12, 13 or 14 digits
+    Ean,
+    /// Id from FEST, e.g. the id of <Legemiddelpakning> or the Oppf id.
+    /// They start with ID_.
+    Id,
+}
+
+impl Identifier {
+    /// Detects the kind of identifier from the format of the value
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::Identifier;
+    ///
+    /// assert_eq!(Identifier::detect("061561"), Some(Identifier::Itemnum));
+    /// assert_eq!(Identifier::detect("7001234567890"), Some(Identifier::Ean));
+    /// assert_eq!(Identifier::detect("ID_0138BA04"), Some(Identifier::Id));
+    /// assert_eq!(Identifier::detect("paracet"), None);
+    /// ```
+    pub fn detect(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let digits = !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit());
+
+        match value.len() {
+            6 if digits => Some(Identifier::Itemnum),
+            8 | 12 | 13 | 14 if digits => Some(Identifier::Ean),
+            _ if value.starts_with("ID_") => Some(Identifier::Id),
+            _ => None,
+        }
+    }
+}
+
+/// The EAN-13 as it is stored in FEST. Scanners can give the GTIN-14
+/// with a leading zero, or the GTIN-12 (UPC) without it.
+pub(crate) fn normalize_ean(ean: &str) -> Cow<'_, str> {
+    let ean = ean.trim();
+
+    match ean.len() {
+        14 => Cow::Borrowed(ean.strip_prefix('0').unwrap_or(ean)),
+        12 => Cow::Owned(format!("0{}", ean)),
+        _ => Cow::Borrowed(ean),
+    }
+}
+
+/// Borrowed entry (Oppf*) from one of the catalogs
+#[derive(Debug, Clone, Copy)]
+pub enum EntryRef<'a> {
+    /// Drug package (OppfLegemiddelpakning)
+    Package(&'a Package),
+    /// Interaction (OppfInteraksjon)
+    Interaction(&'a Interaction),
+    /// Active substance (OppfVirkestoff with a Virkestoff)
+    ActiveSubstance(&'a ActiveSubstance),
+    /// Substance with strength (OppfVirkestoff)
+    SubstanceStrength(&'a SubstanceStrength),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Identifier::detect(" 061561 "), Some(Identifier::Itemnum));
+        assert_eq!(Identifier::detect("07001234567890"), Some(Identifier::Ean));
+        assert_eq!(Identifier::detect("12345"), None);
+        assert_eq!(Identifier::detect("06156a"), None);
+        assert_eq!(Identifier::detect(""), None);
+    }
+
+    #[test]
+    fn test_normalize_ean() {
+        assert_eq!(normalize_ean("07001234567890"), "7001234567890");
+        assert_eq!(normalize_ean("7001234567890"), "7001234567890");
+        assert_eq!(normalize_ean("17001234567890"), "17001234567890");
+        assert_eq!(normalize_ean("012345678905"), "0012345678905");
+    }
+}
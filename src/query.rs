@@ -0,0 +1,335 @@
+use std::cmp::Ordering;
+use std::str::FromStr;
+use crate::error::FestError;
+use crate::index::Index;
+use crate::kith::{Cv, Timestamp};
+use crate::types::Package;
+
+/// The order of the packages from a [`PackageQuery`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Order {
+    /// The order in the fest file
+    #[default]
+    File,
+    /// By the name (NavnFormStyrke)
+    Name,
+    /// By the itemnumber (varenr)
+    Itemnum,
+    /// By the ATC code, then the name
+    Atc,
+}
+
+/// Filters for the packages, run with [`Fest::query`]
+///
+/// The filters are combined, so a package must match all of them. A
+/// filter on the exact ATC code or an exchange group uses the indexes.
+///
+/// The query can also be written as text, with `key:value` separated
+/// by spaces. Values with spaces are quoted.
+///
+/// | Key            | Value                                      |
+/// |----------------|--------------------------------------------|
+/// | `atc`          | ATC code, or the start of it with `*`      |
+/// | `prescription` | Prescription group (Reseptgruppe), e.g. `C` |
+/// | `form`         | Dosage form, the code or the name          |
+/// | `status`       | Status of the entry, e.g. `A`              |
+/// | `exchange`     | `yes`, `no` or the id of the exchange group |
+/// | `marketed`     | On the market at the date, e.g. `2024-09-09` |
+/// | `order`        | `file`, `name`, `itemnum` or `atc`         |
+/// | `limit`        | The most packages to return                |
+///
+/// # Example
+/// ```
+/// use festlib::{Fest, Order, PackageQuery};
+/// let fest = Fest::new("test_fest.xml").unwrap();
+///
+/// let query = PackageQuery::new().atc_prefix("A01AA").form("Kapsel").order_by(Order::Name);
+/// let names: Vec<&String> = fest.query(&query).unwrap().map(|p| p.name()).collect();
+/// assert_eq!(names.len(), 3);
+///
+/// let query: PackageQuery = r#"atc:A01* form:"Kapsel" order:name limit:2"#.parse().unwrap();
+/// assert_eq!(fest.query(&query).unwrap().count(), 2);
+/// ```
+///
+/// [`Fest::query`]: crate::Fest::query
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PackageQuery {
+    atc: Option<String>,
+    atc_prefix: Option<String>,
+    prescription_group: Option<String>,
+    form: Option<String>,
+    status: Option<String>,
+    exchange_group: Option<String>,
+    has_exchange_group: Option<bool>,
+    marketed_on: Option<Timestamp>,
+    order: Order,
+    limit: Option<usize>,
+}
+
+impl PackageQuery {
+    /// A query that matches all the packages
+    pub fn new() -> Self {
+        PackageQuery::default()
+    }
+
+    /// Packages with the ATC code
+    pub fn atc(mut self, code: &str) -> Self {
+        self.atc = Some(code.trim().to_uppercase());
+        self
+    }
+
+    /// Packages with an ATC code that starts with the prefix, e.g. N02B
+    pub fn atc_prefix(mut self, prefix: &str) -> Self {
+        self.atc_prefix = Some(prefix.trim().to_uppercase());
+        self
+    }
+
+    /// Packages in the prescription group (Reseptgruppe), e.g. C
+    pub fn prescription_group(mut self, group: &str) -> Self {
+        self.prescription_group = Some(group.trim().to_string());
+        self
+    }
+
+    /// Packages with the dosage form (LegemiddelformKort), by the code
+    /// or the name, e.g. 32 or Kapsel
+    pub fn form(mut self, form: &str) -> Self {
+        self.form = Some(form.trim().to_string());
+        self
+    }
+
+    /// Packages where the entry has the status, e.g. A
+    pub fn status(mut self, status: &str) -> Self {
+        self.status = Some(status.trim().to_string());
+        self
+    }
+
+    /// Packages in the exchange group
+    pub fn exchange_group(mut self, id: &str) -> Self {
+        self.exchange_group = Some(id.trim().to_string());
+        self
+    }
+
+    /// Packages with or without an exchange group
+    pub fn has_exchange_group(mut self, value: bool) -> Self {
+        self.has_exchange_group = Some(value);
+        self
+    }
+
+    /// Packages on the market at the date, see [`Package::is_marketed`]
+    pub fn marketed_on(mut self, date: Timestamp) -> Self {
+        self.marketed_on = Some(date);
+        self
+    }
+
+    /// The order of the packages. The order in the file is the default.
+    pub fn order_by(mut self, order: Order) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Returns at most this many packages
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Returns true if the package matches all the filters
+    pub fn matches(&self, package: &Package) -> bool {
+        let atc = package.atc().and_then(Cv::v);
+
+        self.atc.as_deref().is_none_or(|a| atc == Some(a))
+            && self.atc_prefix.as_deref().is_none_or(|p| atc.is_some_and(|a| a.starts_with(p)))
+            && self.prescription_group.as_deref().is_none_or(|g| package.group().and_then(|c| c.v()) == Some(g))
+            && self.form.as_deref().is_none_or(|f| package.form().is_some_and(|c| same_form(c, f)))
+            && self.status.as_deref().is_none_or(|s| package.metadata().status().and_then(|c| c.v()) == Some(s))
+            && self.exchange_group.as_deref().is_none_or(|e| package.exchange_id().map(String::as_str) == Some(e))
+            && self.has_exchange_group.is_none_or(|h| package.exchange_id().is_some() == h)
+            && self.marketed_on.is_none_or(|d| package.is_marketed(&d))
+    }
+
+    /// Runs the query. The packages are taken from an index when the
+    /// query has one of the indexed keys, and the rest are filtered.
+    pub(crate) fn run<'a>(&self, packages: &'a [Package], index: &Index) -> Vec<&'a Package> {
+        let positions = match (&self.exchange_group, &self.atc) {
+            (Some(id), _) => Some(index.exchange_group(id)),
+            (None, Some(atc)) => Some(index.atc(atc)),
+            (None, None) => None,
+        };
+
+        let candidates: Box<dyn Iterator<Item = &'a Package>> = match positions {
+            Some(positions) => Box::new(positions.iter().map(|i| &packages[*i])),
+            None => Box::new(packages.iter()),
+        };
+        let matches = candidates.filter(|p| self.matches(p));
+        let limit = self.limit.unwrap_or(usize::MAX);
+
+        // without an order the limit can stop the filtering early
+        if self.order == Order::File {
+            return matches.take(limit).collect();
+        }
+
+        let mut result: Vec<&Package> = matches.collect();
+        result.sort_by(|a, b| self.compare(a, b));
+        result.truncate(limit);
+        result
+    }
+
+    fn compare(&self, a: &Package, b: &Package) -> Ordering {
+        match self.order {
+            Order::File => Ordering::Equal,
+            Order::Name => a.name().cmp(b.name()),
+            Order::Itemnum => a.itemnum().cmp(b.itemnum()),
+            Order::Atc => {
+                let (atc_a, atc_b) = (a.atc().and_then(Cv::v), b.atc().and_then(Cv::v));
+                atc_a.cmp(&atc_b).then_with(|| a.name().cmp(b.name()))
+            }
+        }
+    }
+}
+
+// the code (V) or the name (DN) of the dosage form
+fn same_form(form: &Cv, value: &str) -> bool {
+    form.v() == Some(value) || form.dn().is_some_and(|dn| dn.eq_ignore_ascii_case(value))
+}
+
+impl FromStr for PackageQuery {
+    type Err = FestError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut query = PackageQuery::new();
+
+        for term in terms(text)? {
+            let (key, value) = term
+                .split_once(':')
+                .ok_or_else(|| FestError::Query(format!("expected key:value, found '{}'", term)))?;
+            let invalid = || FestError::Query(format!("invalid value for {}: '{}'", key, value));
+
+            query = match key {
+                "atc" => match value.strip_suffix('*') {
+                    Some(prefix) => query.atc_prefix(prefix),
+                    None => query.atc(value),
+                },
+                "prescription" => query.prescription_group(value),
+                "form" => query.form(value),
+                "status" => query.status(value),
+                "exchange" => match value {
+                    "yes" | "true" => query.has_exchange_group(true),
+                    "no" | "false" => query.has_exchange_group(false),
+                    id => query.exchange_group(id),
+                },
+                "marketed" => query.marketed_on(Timestamp::parse(value).ok_or_else(invalid)?),
+                "order" => query.order_by(match value {
+                    "file" => Order::File,
+                    "name" => Order::Name,
+                    "itemnum" => Order::Itemnum,
+                    "atc" => Order::Atc,
+                    _ => return Err(invalid()),
+                }),
+                "limit" => query.limit(value.parse().map_err(|_| invalid())?),
+                _ => return Err(FestError::Query(format!("unknown key '{}'", key))),
+            };
+        }
+
+        Ok(query)
+    }
+}
+
+// splits the text on spaces, except inside quotes. The quotes are
+// removed.
+fn terms(text: &str) -> Result<Vec<String>, FestError> {
+    let mut terms = Vec::new();
+    let mut term = String::new();
+    let mut quoted = false;
+
+    for c in text.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !term.is_empty() {
+                    terms.push(std::mem::take(&mut term));
+                }
+            }
+            c => term.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(FestError::Query("missing end quote".to_string()));
+    }
+    if !term.is_empty() {
+        terms.push(term);
+    }
+
+    Ok(terms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xml;
+
+    fn run(query: &PackageQuery) -> Vec<String> {
+        let packages = xml::test_packages();
+        let index = Index::new(&packages, &[], &[], &[]);
+
+        query.run(&packages, &index).iter().map(|p| p.itemnum().to_string()).collect()
+    }
+
+    #[test]
+    fn test_filters() {
+        assert_eq!(run(&PackageQuery::new()).len(), 5);
+        assert_eq!(run(&PackageQuery::new().atc("a01aa01")), vec!["061561", "123456", "654321"]);
+        assert_eq!(run(&PackageQuery::new().atc_prefix("A01")).len(), 4);
+        assert_eq!(run(&PackageQuery::new().form("31")), vec!["953335"]);
+        assert_eq!(run(&PackageQuery::new().form("kapsel")).len(), 3);
+        assert_eq!(run(&PackageQuery::new().prescription_group("A")).len(), 5);
+        assert!(run(&PackageQuery::new().prescription_group("C")).is_empty());
+        assert_eq!(run(&PackageQuery::new().status("A")).len(), 5);
+        assert_eq!(run(&PackageQuery::new().has_exchange_group(false)), vec!["017701"]);
+        assert_eq!(run(&PackageQuery::new().exchange_group("BYTTE001").atc("A01AA02")), vec!["953335"]);
+    }
+
+    #[test]
+    fn test_marketed() {
+        // package 2 is temporarily off the market and package 5 isn't
+        // on the market yet
+        let query = PackageQuery::new().marketed_on(Timestamp::from_date(2024, 9, 9));
+        assert_eq!(run(&query), vec!["061561", "123456"]);
+    }
+
+    #[test]
+    fn test_order_and_limit() {
+        let query = PackageQuery::new().order_by(Order::Itemnum).limit(3);
+        assert_eq!(run(&query), vec!["017701", "061561", "123456"]);
+
+        let query = PackageQuery::new().order_by(Order::Atc);
+        assert_eq!(run(&query).last().unwrap(), "017701");
+        assert_eq!(run(&PackageQuery::new().limit(2)), vec!["061561", "953335"]);
+    }
+
+    #[test]
+    fn test_parse() {
+        let query: PackageQuery = r#"atc:A01AA* form:"Kapsel" exchange:yes marketed:2024-09-09 order:name limit:10"#
+            .parse()
+            .unwrap();
+        assert_eq!(query, PackageQuery::new()
+            .atc_prefix("A01AA")
+            .form("Kapsel")
+            .has_exchange_group(true)
+            .marketed_on(Timestamp::from_date(2024, 9, 9))
+            .order_by(Order::Name)
+            .limit(10));
+
+        let query: PackageQuery = "exchange:BYTTE001 atc:A01AA01".parse().unwrap();
+        assert_eq!(run(&query).len(), 3);
+        assert_eq!("".parse::<PackageQuery>().unwrap(), PackageQuery::new());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for text in ["atc", "color:red", "limit:many", "order:price", "marketed:i dag", r#"form:"Kapsel"#] {
+            let error = text.parse::<PackageQuery>().unwrap_err();
+            assert!(matches!(error, FestError::Query(_)), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::catalog::Catalog;
+use crate::kith::Timestamp;
+use crate::schema::SchemaVersion;
+
+/// Information about the fest file, captured when it is loaded
+///
+/// # Example
+/// ```
+/// use festlib::{Catalog, Fest};
+///
+/// let fest = Fest::new("test_fest.xml").unwrap();
+/// let release = fest.release();
+///
+/// assert_eq!(release.delivery_date().unwrap().date(), Some((2024, 9, 9)));
+/// assert_eq!(release.entry_count(Catalog::Packages), Some(5));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseInfo {
+    delivery_date: Option<Timestamp>,
+    schema_version: SchemaVersion,
+    source: Option<PathBuf>,
+    content_hash: u64,
+    entry_counts: Vec<(Catalog, usize)>,
+    load_duration: Duration,
+}
+
+impl ReleaseInfo {
+    pub(crate) fn from(
+        delivery_date: Option<Timestamp>,
+        schema_version: SchemaVersion,
+        source: Option<PathBuf>,
+        content_hash: u64,
+        entry_counts: Vec<(Catalog, usize)>,
+        load_duration: Duration,
+    ) -> Self {
+        ReleaseInfo {
+            delivery_date,
+            schema_version,
+            source,
+            content_hash,
+            entry_counts,
+            load_duration,
+        }
+    }
+
+    /// Returns the last update of the fest file (HentetDato). None if
+    /// HentetDato is missing or isn't a valid dateTime.
+    pub fn delivery_date(&self) -> Option<Timestamp> {
+        self.delivery_date
+    }
+
+    /// Returns the version of the fest message (M30)
+    pub fn schema_version(&self) -> SchemaVersion {
+        self.schema_version
+    }
+
+    /// Returns the path of the fest file. None if it was read from
+    /// memory or a reader.
+    pub fn source(&self) -> Option<&Path> {
+        self.source.as_deref()
+    }
+
+    /// Returns the hash of the xml content. Zip archives are hashed
+    /// after the xml is read from the archive.
+    pub fn content_hash(&self) -> u64 {
+        self.content_hash
+    }
+
+    /// Returns the number of entries (Oppf*) in the catalog, including
+    /// entries that could not be parsed. None if the catalog isn't loaded.
+    pub fn entry_count(&self, catalog: Catalog) -> Option<usize> {
+        self.entry_counts.iter().find(|(c, _)| *c == catalog).map(|(_, count)| *count)
+    }
+
+    /// Returns how long it took to parse the fest file and build the
+    /// indexes
+    pub fn load_duration(&self) -> Duration {
+        self.load_duration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use crate::{Fest, FestBuilder};
+
+    #[test]
+    fn test_release_info() {
+        let fest = Fest::new("test_fest.xml").unwrap();
+        let release = fest.release();
+
+        assert_eq!(release.delivery_date(), Timestamp::parse("2024-09-09T14:21:28"));
+        assert_eq!(release.schema_version(), SchemaVersion::V2_5_1);
+        assert_eq!(release.source(), Some(Path::new("test_fest.xml")));
+        assert_eq!(release.content_hash(), fest.content_hash());
+        assert_eq!(release.entry_count(Catalog::Interactions), Some(1));
+
+        // the entries without strength are counted, but not parsed as strengths
+        assert_eq!(release.entry_count(Catalog::SubstanceStrengths), Some(4));
+        assert_eq!(fest.substance_strengths().len(), 2);
+    }
+
+    #[test]
+    fn test_from_memory() {
+        let content = std::fs::read_to_string("test_fest_v25.xml").unwrap();
+        let fest = Fest::from_str(&content).unwrap();
+
+        assert_eq!(fest.release().source(), None);
+        assert_eq!(fest.release().schema_version(), SchemaVersion::V2_5);
+        assert_eq!(fest.release().entry_count(Catalog::SubstanceStrengths), Some(0));
+    }
+
+    #[test]
+    fn test_not_loaded() {
+        let fest = FestBuilder::new().catalog(Catalog::Packages).load("test_fest.xml").unwrap();
+
+        assert_eq!(fest.release().entry_count(Catalog::Packages), Some(5));
+        assert_eq!(fest.release().entry_count(Catalog::Interactions), None);
+    }
+}
@@ -0,0 +1,347 @@
+//! Diagnostics collected while parsing the fest xml file
+//!
+//! The parsers are lenient: a missing optional value becomes None, and
+//! an entry that can't be read or misses a required field is skipped.
+//! The [`ParseReport`] lists where that happened, so a broken file
+//! isn't silently accepted.
+//! The skipped entries are recorded by the parsers, so the report
+//! matches what was loaded. The other warnings are found by checking
+//! the entries.
+
+use std::fmt;
+use roxmltree::{Document, Node};
+use serde::{Deserialize, Serialize};
+use crate::catalog::Catalog;
+use crate::xml;
+
+/// What is wrong with the entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WarningKind {
+    /// A required element is missing or empty
+    MissingField(String),
+    /// A coded value (CS or CV) has no code and no NULL flavor
+    EmptyCode(String),
+    /// An element that isn't part of the entry
+    UnknownElement(String),
+    /// The entry could not be read and is not part of the result
+    SkippedEntry(String),
+}
+
+/// A warning for a single entry (Oppf*) in the fest file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Warning {
+    kind: WarningKind,
+    entry_id: String,
+    line: u32,
+    column: u32,
+}
+
+impl Warning {
+    /// Returns what is wrong with the entry
+    pub fn kind(&self) -> &WarningKind {
+        &self.kind
+    }
+
+    /// Returns the id of the entry (Oppf id). Empty if the entry
+    /// has no id.
+    pub fn entry_id(&self) -> &str {
+        &self.entry_id
+    }
+
+    /// Returns the line of the element in the fest file
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Returns the column of the element in the fest file
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{} ", self.line, self.column)?;
+
+        match &self.kind {
+            WarningKind::MissingField(e) => write!(f, "missing <{}>", e)?,
+            WarningKind::EmptyCode(e) => write!(f, "empty code in <{}>", e)?,
+            WarningKind::UnknownElement(e) => write!(f, "unknown element <{}>", e)?,
+            WarningKind::SkippedEntry(e) => write!(f, "skipped <{}>", e)?,
+        }
+
+        if !self.entry_id.is_empty() {
+            write!(f, " in entry {}", self.entry_id)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The warnings found while parsing the fest file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ParseReport {
+    warnings: Vec<Warning>,
+}
+
+impl ParseReport {
+    /// Returns all the warnings
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Returns true if there are no warnings
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty()
+    }
+
+    /// Returns the number of warnings
+    pub fn len(&self) -> usize {
+        self.warnings.len()
+    }
+
+    /// Records an entry (Oppf*) the parser skipped
+    pub(crate) fn skipped(&mut self, node: &Node) {
+        let position = node.document().text_pos_at(node.range().start);
+        self.skipped_at(node, (position.row, position.col));
+    }
+
+    /// Records an entry (Oppf*) the parser skipped, at the line and
+    /// column in the fest file. Used when the entry is parsed on its
+    /// own, as with [`FestReader`](crate::FestReader).
+    pub(crate) fn skipped_at(&mut self, node: &Node, (line, column): (u32, u32)) {
+        let id = xml::string_value(node, "Id").unwrap_or_default();
+
+        self.warnings.push(Warning {
+            kind: WarningKind::SkippedEntry(node.tag_name().name().to_string()),
+            entry_id: id,
+            line,
+            column,
+        });
+    }
+
+    /// Adds the warnings from the other report, and sorts all the
+    /// warnings by their position in the file
+    pub(crate) fn merge(&mut self, other: ParseReport) {
+        self.warnings.extend(other.warnings);
+        self.warnings.sort_by_key(|w| (w.line, w.column));
+    }
+
+    fn push(&mut self, document: &Document, node: &Node, entry_id: &str, kind: WarningKind) {
+        let position = document.text_pos_at(node.range().start);
+
+        self.warnings.push(Warning {
+            kind,
+            entry_id: entry_id.to_string(),
+            line: position.row,
+            column: position.col,
+        });
+    }
+}
+
+/// The entries festlib reads, with the element inside the entry and
+/// the required fields of that element
+struct Entry {
+    catalog: &'static str,
+    entry: &'static str,
+    element: &'static [&'static str],
+    required: &'static [&'static str],
+}
+
+const ENTRIES: &[Entry] = &[
+    Entry {
+        catalog: "KatLegemiddelpakning",
+        entry: "OppfLegemiddelpakning",
+        element: &["Legemiddelpakning"],
+        required: &["Atc", "NavnFormStyrke", "Reseptgruppe", "Varenr"],
+    },
+    Entry {
+        catalog: "KatInteraksjon",
+        entry: "OppfInteraksjon",
+        element: &["Interaksjon"],
+        required: &["Id", "Relevans", "Substansgruppe"],
+    },
+    Entry {
+        catalog: "KatVirkestoff",
+        entry: "OppfVirkestoff",
+        element: &["VirkestoffMedStyrke", "Virkestoff"],
+        required: &[],
+    },
+];
+
+// the fields every entry has (Enkeltoppforing)
+const ENTRY_FIELDS: &[&str] = &["Id", "Tidspunkt", "Status"];
+
+// the required fields when the substance has a strength
+const STRENGTH_FIELDS: &[&str] = &["Id", "Styrke", "RefVirkestoff"];
+
+// the required fields of an active substance
+const SUBSTANCE_FIELDS: &[&str] = &["Id", "Navn"];
+
+/// Checks the entries of all the catalogs festlib reads
+pub(crate) fn check(document: &Document, catalogs: &[Catalog]) -> ParseReport {
+    let mut report = ParseReport::default();
+
+    for e in ENTRIES.iter().filter(|e| catalogs.iter().any(|c| c.name() == e.catalog)) {
+        let Some(catalog) = xml::catalog(document, e.catalog) else {
+            continue;
+        };
+
+        for node in catalog.children().filter(|n| n.is_element()) {
+            if node.has_tag_name(e.entry) {
+                check_entry(&mut report, document, &node, e);
+            } else {
+                let kind = WarningKind::SkippedEntry(node.tag_name().name().to_string());
+                report.push(document, &node, "", kind);
+            }
+        }
+    }
+
+    report
+}
+
+fn check_entry(report: &mut ParseReport, document: &Document, node: &Node, entry: &Entry) {
+    let id = xml::string_value(node, "Id").unwrap_or_default();
+
+    for field in ENTRY_FIELDS {
+        missing(report, document, node, &id, field);
+    }
+
+    for child in node.children().filter(|n| n.is_element()) {
+        let name = child.tag_name().name();
+        if !ENTRY_FIELDS.contains(&name) && !entry.element.contains(&name) {
+            report.push(document, &child, &id, WarningKind::UnknownElement(name.to_string()));
+        }
+    }
+
+    // the parser records the entry as skipped
+    let Some(element) = node.children().find(|n| entry.element.iter().any(|e| n.has_tag_name(*e))) else {
+        return;
+    };
+
+    let required = match element.tag_name().name() {
+        "VirkestoffMedStyrke" => STRENGTH_FIELDS,
+        "Virkestoff" => SUBSTANCE_FIELDS,
+        _ => entry.required,
+    };
+
+    for field in required {
+        missing(report, document, &element, &id, field);
+    }
+
+    for code in node.descendants().filter(|n| n.is_element()) {
+        if empty_code(&code) {
+            let kind = WarningKind::EmptyCode(code.tag_name().name().to_string());
+            report.push(document, &code, &id, kind);
+        }
+    }
+}
+
+// warns if the field is missing, or has no text and no attributes
+fn missing(report: &mut ParseReport, document: &Document, node: &Node, id: &str, field: &str) {
+    let empty = match node.children().find(|n| n.has_tag_name(field)) {
+        Some(n) => n.attributes().len() == 0
+            && !n.children().any(|c| c.is_element())
+            && n.text().unwrap_or("").trim().is_empty(),
+        None => true,
+    };
+
+    if empty {
+        report.push(document, node, id, WarningKind::MissingField(field.to_string()));
+    }
+}
+
+// a coded value (V, S or DN attributes) without a code and no NULL flavor
+fn empty_code(node: &Node) -> bool {
+    let coded = node.has_attribute("V") || node.has_attribute("S") || node.has_attribute("DN");
+    let unit = node.has_attribute("U");
+
+    coded
+        && !unit
+        && !node.has_attribute("NULL")
+        && node.attribute("V").unwrap_or("").trim().is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const BROKEN: &str = r#"<FEST>
+    <KatLegemiddelpakning>
+        <OppfLegemiddelpakning>
+            <Id>ID_1</Id>
+            <Tidspunkt>2024-04-21T00:51:31</Tidspunkt>
+            <Status V="A"/>
+            <Legemiddelpakning>
+                <NavnFormStyrke>Test</NavnFormStyrke>
+                <Reseptgruppe V="" DN="Reseptfri"/>
+            </Legemiddelpakning>
+            <Ukjent/>
+        </OppfLegemiddelpakning>
+        <OppfLegemiddelpakning>
+            <Id>ID_2</Id>
+            <Tidspunkt>2024-04-21T00:51:31</Tidspunkt>
+            <Status V="A"/>
+        </OppfLegemiddelpakning>
+        <OppfInteraksjon/>
+    </KatLegemiddelpakning>
+</FEST>"#;
+
+    #[test]
+    fn test_no_warnings() {
+        let content = fs::read_to_string("test_fest.xml").unwrap();
+        let document = xml::document(&content).unwrap();
+
+        let report = check(&document, &Catalog::ALL);
+        assert!(report.is_empty(), "{:?}", report);
+    }
+
+    #[test]
+    fn test_warnings() {
+        let document = xml::document(BROKEN).unwrap();
+        let report = check(&document, &Catalog::ALL);
+
+        let kinds: Vec<(&WarningKind, &str)> = report.warnings()
+            .iter()
+            .map(|w| (w.kind(), w.entry_id()))
+            .collect();
+
+        assert_eq!(kinds, vec![
+            (&WarningKind::UnknownElement("Ukjent".to_string()), "ID_1"),
+            (&WarningKind::MissingField("Atc".to_string()), "ID_1"),
+            (&WarningKind::MissingField("Varenr".to_string()), "ID_1"),
+            (&WarningKind::EmptyCode("Reseptgruppe".to_string()), "ID_1"),
+            (&WarningKind::SkippedEntry("OppfInteraksjon".to_string()), ""),
+        ]);
+    }
+
+    #[test]
+    fn test_skipped() {
+        let document = xml::document(BROKEN).unwrap();
+        let mut report = ParseReport::default();
+        let packages = xml::packages(&document, &mut report);
+
+        let skipped: Vec<(&WarningKind, &str)> = report.warnings()
+            .iter()
+            .map(|w| (w.kind(), w.entry_id()))
+            .collect();
+
+        // ID_1 has no Varenr
+        assert!(packages.is_empty());
+        assert_eq!(skipped, vec![
+            (&WarningKind::SkippedEntry("OppfLegemiddelpakning".to_string()), "ID_1"),
+            (&WarningKind::SkippedEntry("OppfLegemiddelpakning".to_string()), "ID_2"),
+        ]);
+    }
+
+    #[test]
+    fn test_position() {
+        let document = xml::document(BROKEN).unwrap();
+        let report = check(&document, &Catalog::ALL);
+
+        let warning = &report.warnings()[3];
+        assert_eq!((warning.line(), warning.column()), (9, 17));
+        assert_eq!(warning.to_string(), "9:17 empty code in <Reseptgruppe> in entry ID_1");
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+use roxmltree::{Document, Node};
+use serde::{Deserialize, Serialize};
+use crate::error::FestError;
+use crate::report::ParseReport;
+use crate::types::Package;
+use crate::{legacy, xml};
+
+/// Namespace of M30 v2.5 (FEST 2013-10-08)
+const M30_2013: &str = "http://www.kith.no/xmlstds/eresept/m30/2013-10-08";
+
+/// Namespace of M30 v2.5.1 (FEST 2014-12-01), the format of fest251.xml
+const M30_2014: &str = "http://www.kith.no/xmlstds/eresept/m30/2014-12-01";
+
+/// The version of the fest message (M30), found from the namespace
+/// of the <FEST> element.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SchemaVersion {
+    /// M30 v2.5 (2013-10-08)
+    V2_5,
+    /// M30 v2.5.1 (2014-12-01), used by FEST v3.x (fest251.xml)
+    #[default]
+    V2_5_1,
+}
+
+impl SchemaVersion {
+    /// Finds the version of the document. A document without namespace
+    /// is read as the current version. Documents with an unknown
+    /// namespace are refused.
+    pub(crate) fn detect(document: &Document) -> Result<Self, FestError> {
+        let root = document.root_element();
+        SchemaVersion::from_root(root.tag_name().name(), root.tag_name().namespace())
+    }
+
+    /// Finds the version from the name and namespace of the root element
+    pub(crate) fn from_root(name: &str, namespace: Option<&str>) -> Result<Self, FestError> {
+        if name != "FEST" {
+            return Err(FestError::UnsupportedSchema(format!("<{}>", name)));
+        }
+
+        match namespace {
+            None | Some(M30_2014) => Ok(SchemaVersion::V2_5_1),
+            Some(M30_2013) => Ok(SchemaVersion::V2_5),
+            Some(namespace) => Err(FestError::UnsupportedSchema(namespace.to_string())),
+        }
+    }
+
+    /// Parses the <OppfLegemiddelpakning> with the parser of the version
+    pub(crate) fn package(&self, node: &Node) -> Option<Package> {
+        match self {
+            SchemaVersion::V2_5 => legacy::package(node),
+            SchemaVersion::V2_5_1 => xml::package(node),
+        }
+    }
+
+    /// Parses all the packages with the parser of the version
+    pub(crate) fn packages(&self, document: &Document, report: &mut ParseReport) -> Vec<Package> {
+        match self {
+            SchemaVersion::V2_5 => legacy::packages(document, report),
+            SchemaVersion::V2_5_1 => xml::packages(document, report),
+        }
+    }
+
+    /// Returns the namespace of the version
+    pub fn namespace(&self) -> &'static str {
+        match self {
+            SchemaVersion::V2_5 => M30_2013,
+            SchemaVersion::V2_5_1 => M30_2014,
+        }
+    }
+
+    /// Returns the version number, e.g. 2.5.1
+    pub fn version(&self) -> &'static str {
+        match self {
+            SchemaVersion::V2_5 => "2.5",
+            SchemaVersion::V2_5_1 => "2.5.1",
+        }
+    }
+}
+
+impl fmt::Display for SchemaVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "M30 v{}", self.version())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(content: &str) -> Result<SchemaVersion, FestError> {
+        SchemaVersion::detect(&Document::parse(content).unwrap())
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect("<FEST/>").unwrap(), SchemaVersion::V2_5_1);
+        assert_eq!(detect(&format!(r#"<FEST xmlns="{}"/>"#, M30_2014)).unwrap(), SchemaVersion::V2_5_1);
+        assert_eq!(detect(&format!(r#"<m30:FEST xmlns:m30="{}"/>"#, M30_2013)).unwrap(), SchemaVersion::V2_5);
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let result = detect(r#"<FEST xmlns="http://www.kith.no/xmlstds/eresept/m30/2099-01-01"/>"#);
+        assert!(matches!(result, Err(FestError::UnsupportedSchema(n)) if n.ends_with("2099-01-01")));
+
+        assert!(matches!(detect("<M1/>"), Err(FestError::UnsupportedSchema(_))));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(SchemaVersion::V2_5_1.to_string(), "M30 v2.5.1");
+        assert_eq!(SchemaVersion::V2_5.namespace(), M30_2013);
+    }
+}
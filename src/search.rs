@@ -0,0 +1,260 @@
+//! Fuzzy search in the names of the packages (NavnFormStyrke)
+//!
+//! The name starts with the brand name, e.g. "Paracet Tab 500 mg", so
+//! brand names are found through the name. Both the query and the names
+//! are normalized: lower case, æ, ø and å as ae, oe and aa (the way
+//! they are written on keyboards without them), other diacritics
+//! removed, and numbers split from the units ("500mg" is "500 mg").
+
+use std::cmp::Ordering;
+use crate::types::Package;
+
+/// A package found by [`Fest::search`], with how well it matches
+///
+/// [`Fest::search`]: crate::Fest::search
+#[derive(Debug, Clone, Copy)]
+pub struct SearchResult<'a> {
+    package: &'a Package,
+    score: f32,
+}
+
+impl<'a> SearchResult<'a> {
+    /// Returns the package
+    pub fn package(&self) -> &'a Package {
+        self.package
+    }
+
+    /// Returns the relevance, from 0 to 1 where 1 is an exact match
+    /// of every word
+    pub fn score(&self) -> f32 {
+        self.score
+    }
+}
+
+/// Searches the packages, best match first. Every word in the query
+/// must match a word in the name.
+pub(crate) fn search<'a>(packages: &'a [Package], query: &str) -> Vec<SearchResult<'a>> {
+    let query = words(query);
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut results: Vec<SearchResult> = packages
+        .iter()
+        .filter_map(|package| {
+            let score = score(&query, &words(package.name()))?;
+            Some(SearchResult { package, score })
+        })
+        .collect();
+
+    // shorter names first when the score is the same, since they are
+    // closer to what was typed
+    results.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| a.package.name().len().cmp(&b.package.name().len()))
+            .then_with(|| a.package.name().cmp(b.package.name()))
+    });
+
+    results
+}
+
+/// Splits the text into normalized words
+pub(crate) fn words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+
+    for c in text.chars().flat_map(char::to_lowercase) {
+        let folded = fold(c);
+
+        // split between numbers and letters, "500mg" is "500" and "mg"
+        let boundary = word
+            .chars()
+            .last()
+            .is_some_and(|l| l.is_ascii_digit() != folded.starts_with(|f: char| f.is_ascii_digit()));
+
+        if (folded.is_empty() || boundary) && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        word.push_str(folded);
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+// folds a lower case character to ascii. Characters that separate
+// words are empty.
+fn fold(c: char) -> &'static str {
+    match c {
+        'æ' | 'ä' => "ae",
+        'ø' | 'ö' => "oe",
+        'å' => "aa",
+        'á' | 'à' | 'â' | 'ã' => "a",
+        'é' | 'è' | 'ê' | 'ë' => "e",
+        'í' | 'ì' | 'î' | 'ï' => "i",
+        'ó' | 'ò' | 'ô' | 'õ' => "o",
+        'ú' | 'ù' | 'û' | 'ü' => "u",
+        'ç' => "c",
+        'ñ' => "n",
+        'ß' => "ss",
+        c if c.is_ascii_alphanumeric() => ascii(c),
+        _ => "",
+    }
+}
+
+fn ascii(c: char) -> &'static str {
+    const ASCII: &str = "0123456789abcdefghijklmnopqrstuvwxyz";
+    let i = ASCII.find(c).unwrap_or(0);
+    &ASCII[i..i + 1]
+}
+
+/// Scores the name for the query, or None if a word in the query
+/// doesn't match. The brand name is the first word, and a match on it
+/// counts more.
+fn score(query: &[String], name: &[String]) -> Option<f32> {
+    let mut total = 0.0;
+
+    for (i, word) in query.iter().enumerate() {
+        let (position, score) = name
+            .iter()
+            .enumerate()
+            .filter_map(|(p, n)| Some((p, word_score(word, n)?)))
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))?;
+
+        // the first word typed is most often the brand name
+        let bonus = if i == 0 && position == 0 { 1.0 } else { 0.9 };
+        total += score * bonus;
+    }
+
+    Some(total / query.len() as f32)
+}
+
+// how well a word in the query matches a word in the name
+fn word_score(query: &str, name: &str) -> Option<f32> {
+    if query == name {
+        return Some(1.0);
+    }
+
+    // numbers (strengths) must match exactly, "50" is not "500"
+    if query.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    if name.starts_with(query) {
+        return Some(0.9);
+    }
+
+    // typos, compared with the start of the name so that "parcet"
+    // also finds "paracetamol"
+    let allowed = match query.len() {
+        0..=3 => return None,
+        4..=6 => 1,
+        _ => 2,
+    };
+
+    let prefix: String = name.chars().take(query.len() + 1).collect();
+    let distance = distance(query, &prefix).min(distance(query, &prefix[..prefix.len().min(query.len())]));
+
+    match distance <= allowed {
+        true => Some(0.8 - 0.1 * distance as f32),
+        false => None,
+    }
+}
+
+/// Damerau-Levenshtein distance (optimal string alignment), where a
+/// swap of two letters is one typo
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut d = (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d = d.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = d;
+        }
+    }
+
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(query: &str, name: &str) -> Option<f32> {
+        score(&words(query), &words(name))
+    }
+
+    #[test]
+    fn test_words() {
+        assert_eq!(words("Paracet Tab 500mg"), vec!["paracet", "tab", "500", "mg"]);
+        assert_eq!(words("Ørebetennelse Bæreløsning"), vec!["oerebetennelse", "baereloesning"]);
+        assert_eq!(words("Åpen 5 mg/ml"), vec!["aapen", "5", "mg", "ml"]);
+        assert_eq!(words("Crème"), vec!["creme"]);
+    }
+
+    #[test]
+    fn test_norwegian() {
+        assert!(matches("bæreløsning", "Baereloesning 10 ml").is_some());
+        assert!(matches("BÆRELØSNING", "bæreløsning 10 ml").is_some());
+
+        // ø typed as o is a typo
+        assert!(matches("baerelosning", "Bæreløsning 10 ml").is_some());
+    }
+
+    #[test]
+    fn test_prefix_and_numbers() {
+        let score = matches("paracet 500", "Paracetamol B. Braun Tab 500 mg").unwrap();
+        assert!(score > 0.8);
+
+        assert!(matches("paracet 50", "Paracetamol Tab 500 mg").is_none());
+        assert!(matches("paracet 400", "Paracetamol Tab 500 mg").is_none());
+    }
+
+    #[test]
+    fn test_typos() {
+        assert!(matches("parcaet", "Paracet Tab 500 mg").is_some());
+        assert!(matches("paracteamol", "Paracetamol Tab 500 mg").is_some());
+        assert!(matches("paracetmol", "Paracetamol Tab 500 mg").is_some());
+
+        // no typos in short words
+        assert!(matches("ibx", "Ibux 400 mg").is_none());
+        assert!(matches("xyzzy", "Paracet Tab 500 mg").is_none());
+    }
+
+    #[test]
+    fn test_ranking() {
+        let exact = matches("paracet", "Paracet Tab 500 mg").unwrap();
+        let prefix = matches("paracet", "Paracetamol Tab 500 mg").unwrap();
+        let later = matches("paracet", "Pinex Paracet 500 mg").unwrap();
+        let typo = matches("parcaet", "Paracet Tab 500 mg").unwrap();
+
+        assert!(exact > prefix);
+        assert!(exact > later);
+        assert!(prefix > typo);
+    }
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("paracet", "paracet"), 0);
+        assert_eq!(distance("parcaet", "paracet"), 1);
+        assert_eq!(distance("ibux", "ibuks"), 2);
+    }
+}
@@ -0,0 +1,202 @@
+//! Binary snapshot of a parsed fest file
+//!
+//! A snapshot is a flat byte buffer, so it can be written to disk and
+//! read from any byte buffer. The fest is deserialized into owned
+//! values when loaded:
+//!
+//! ```text
+//! FESTSNAP | format (u32) | header length (u32) | header | fest
+//! ```
+//!
+//! Numbers are little endian, and the header and the fest are bincode.
+//! The header tells which festlib made the snapshot, and which fest
+//! file it was made from. Snapshots from another festlib version or
+//! snapshot format are rejected, since the data layout can differ.
+
+use std::io::Write;
+use serde::{Deserialize, Serialize};
+use crate::error::FestError;
+use crate::fest::Fest;
+use crate::schema::SchemaVersion;
+
+const MAGIC: &[u8; 8] = b"FESTSNAP";
+
+/// The layout of the snapshot. Bump when the header or the serialized
+/// types change.
+const FORMAT: u32 = 8;
+
+const FESTLIB_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Describes the snapshot and the fest file it was made from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    festlib_version: String,
+    schema_version: SchemaVersion,
+    delivery_date: Option<String>,
+    content_hash: u64,
+}
+
+impl SnapshotInfo {
+    /// Returns the version of festlib that made the snapshot
+    pub fn festlib_version(&self) -> &str {
+        &self.festlib_version
+    }
+
+    /// Returns the version of the fest message (M30)
+    pub fn schema_version(&self) -> SchemaVersion {
+        self.schema_version
+    }
+
+    /// Returns the last update (HentetDato) of the fest file
+    pub fn delivery_date(&self) -> Option<&str> {
+        self.delivery_date.as_deref()
+    }
+
+    /// Returns the hash of the xml content of the fest file
+    pub fn content_hash(&self) -> u64 {
+        self.content_hash
+    }
+}
+
+/// Hash of the xml content (64 bit FNV-1a). The hash is stable between
+/// builds and platforms, so it can be compared with a stored hash.
+pub(crate) fn content_hash(content: &[u8]) -> u64 {
+    content.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Writes the snapshot of the fest
+pub(crate) fn write<W: Write>(mut writer: W, fest: &Fest) -> Result<(), FestError> {
+    let info = SnapshotInfo {
+        festlib_version: FESTLIB_VERSION.to_string(),
+        schema_version: fest.schema_version(),
+        delivery_date: fest.delivery_date().date().map(String::from),
+        content_hash: fest.content_hash(),
+    };
+    let header = bincode::serialize(&info).map_err(error)?;
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&FORMAT.to_le_bytes())?;
+    writer.write_all(&(header.len() as u32).to_le_bytes())?;
+    writer.write_all(&header)?;
+    bincode::serialize_into(&mut writer, fest).map_err(error)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Reads the header of the snapshot, and returns it with the rest of
+/// the snapshot
+pub(crate) fn info(bytes: &[u8]) -> Result<(SnapshotInfo, &[u8]), FestError> {
+    let invalid = || FestError::Snapshot("not a festlib snapshot".to_string());
+
+    let bytes = bytes.strip_prefix(MAGIC).ok_or_else(invalid)?;
+    let (format, bytes) = read_u32(bytes).ok_or_else(invalid)?;
+    if format != FORMAT {
+        return Err(FestError::Snapshot(format!("snapshot format {} is not supported", format)));
+    }
+
+    let (length, bytes) = read_u32(bytes).ok_or_else(invalid)?;
+    if bytes.len() < length as usize {
+        return Err(invalid());
+    }
+    let (header, bytes) = bytes.split_at(length as usize);
+    let info: SnapshotInfo = bincode::deserialize(header).map_err(error)?;
+
+    if info.festlib_version != FESTLIB_VERSION {
+        return Err(FestError::Snapshot(format!(
+            "made by festlib {}, this is festlib {}",
+            info.festlib_version, FESTLIB_VERSION
+        )));
+    }
+
+    Ok((info, bytes))
+}
+
+/// Reads the fest from the snapshot
+pub(crate) fn read(bytes: &[u8]) -> Result<Fest, FestError> {
+    let (info, bytes) = info(bytes)?;
+    let fest: Fest = bincode::deserialize(bytes).map_err(error)?;
+
+    if fest.schema_version() != info.schema_version || fest.content_hash() != info.content_hash {
+        return Err(FestError::Snapshot("the header doesn't match the data".to_string()));
+    }
+
+    Ok(fest)
+}
+
+fn read_u32(bytes: &[u8]) -> Option<(u32, &[u8])> {
+    let (number, rest) = bytes.split_first_chunk::<4>()?;
+    Some((u32::from_le_bytes(*number), rest))
+}
+
+fn error(error: bincode::Error) -> FestError {
+    FestError::Snapshot(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> Vec<u8> {
+        let fest = Fest::new("test_fest.xml").unwrap();
+        let mut bytes = Vec::new();
+        write(&mut bytes, &fest).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let fest = read(&snapshot()).unwrap();
+
+        assert_eq!(fest.delivery_date().date(), Some("2024-09-09T14:21:28"));
+        assert_eq!(fest.packages().len(), 5);
+        assert_eq!(fest.find_package("061561").unwrap().unwrap().ean().unwrap(), "7001234567890");
+        assert_eq!(fest.content_hash(), content_hash(&std::fs::read("test_fest.xml").unwrap()));
+    }
+
+    #[test]
+    fn test_info() {
+        let bytes = snapshot();
+        let (info, _) = info(&bytes).unwrap();
+
+        assert_eq!(info.festlib_version(), FESTLIB_VERSION);
+        assert_eq!(info.schema_version(), SchemaVersion::V2_5_1);
+        assert_eq!(info.delivery_date(), Some("2024-09-09T14:21:28"));
+    }
+
+    #[test]
+    fn test_rejected() {
+        assert!(matches!(read(b"<FEST/>"), Err(FestError::Snapshot(_))));
+
+        // another snapshot format
+        let mut bytes = snapshot();
+        bytes[8] = 99;
+        assert!(matches!(read(&bytes), Err(FestError::Snapshot(e)) if e.contains("format 99")));
+
+        // another festlib version
+        let info = SnapshotInfo {
+            festlib_version: "0.0.1".to_string(),
+            schema_version: SchemaVersion::V2_5_1,
+            delivery_date: None,
+            content_hash: 0,
+        };
+        let header = bincode::serialize(&info).unwrap();
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(FORMAT.to_le_bytes());
+        bytes.extend((header.len() as u32).to_le_bytes());
+        bytes.extend(header);
+        assert!(matches!(read(&bytes), Err(FestError::Snapshot(e)) if e.contains("0.0.1")));
+
+        // truncated
+        let bytes = snapshot();
+        assert!(read(&bytes[..bytes.len() - 10]).is_err());
+    }
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(content_hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(content_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
@@ -0,0 +1,101 @@
+//! Counts of the packages per group, for reports
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::atc::AtcTree;
+use crate::types::Package;
+
+/// The number of packages with a code, e.g. a prescription group
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Count {
+    code: String,
+    name: Option<String>,
+    count: usize,
+}
+
+impl Count {
+    /// Returns the code (V)
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Returns the name of the code (DN)
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the number of packages
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+/// Counts the packages by the code and name the function returns.
+/// Packages without a code are not counted. The counts are sorted by
+/// code, and the first name found for a code is used.
+pub(crate) fn count_by<'a, F>(packages: &'a [Package], code: F) -> Vec<Count>
+where
+    F: Fn(&'a Package) -> Option<(&'a str, Option<&'a str>)>,
+{
+    let mut counts: BTreeMap<&str, Count> = BTreeMap::new();
+
+    for (v, dn) in packages.iter().filter_map(code) {
+        let count = counts.entry(v).or_insert_with(|| Count {
+            code: v.to_string(),
+            name: None,
+            count: 0,
+        });
+
+        count.count += 1;
+        if count.name.is_none() {
+            count.name = dn.map(str::to_string);
+        }
+    }
+
+    counts.into_values().collect()
+}
+
+/// Counts the packages in the ATC groups on the level (1 to 5), with
+/// the names from the tree
+pub(crate) fn count_by_atc(tree: &AtcTree, level: u8) -> Vec<Count> {
+    tree.level(level)
+        .filter(|n| n.package_count() > 0)
+        .map(|n| Count {
+            code: n.code().to_string(),
+            name: n.name().map(str::to_string),
+            count: n.package_count(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xml::test_packages;
+
+    #[test]
+    fn test_count_by() {
+        let packages = test_packages();
+
+        let forms = count_by(&packages, |p| p.form().and_then(|f| Some((f.v()?, f.dn()))));
+        let forms: Vec<(&str, Option<&str>, usize)> = forms.iter().map(|c| (c.code(), c.name(), c.count())).collect();
+        assert_eq!(forms, vec![("31", Some("Tablet"), 1), ("32", Some("Kapsel"), 3)]);
+
+        let groups = count_by(&packages, |p| p.group().and_then(|g| Some((g.v()?, g.dn()))));
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].count(), 5);
+    }
+
+    #[test]
+    fn test_count_by_atc() {
+        let packages = test_packages();
+        let tree = AtcTree::new(&packages, &[]);
+
+        let counts = count_by_atc(&tree, 1);
+        let counts: Vec<(&str, usize)> = counts.iter().map(|c| (c.code(), c.count())).collect();
+        assert_eq!(counts, vec![("A", 4), ("B", 1)]);
+
+        let counts = count_by_atc(&tree, 5);
+        assert_eq!(counts[0].name(), Some("Test ATC Code"));
+    }
+}
@@ -0,0 +1,485 @@
+//! Streaming parser for the fest xml file
+//!
+//! [`FestReader`] reads the file one `<Oppf*>` entry at a time, so the
+//! memory used is bounded by the size of the largest entry and not by
+//! the size of the file. Each entry is parsed with the same parsers as
+//! [`Fest`](crate::Fest), so the values are identical, and the entries
+//! the parsers skip are listed in [`FestReader::report`].
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::{Reader, Writer};
+use crate::error::FestError;
+use crate::report::ParseReport;
+use crate::schema::SchemaVersion;
+use crate::types::{ActiveSubstance, Interaction, Package, SubstanceStrength};
+use crate::xml;
+
+/// An entry read from the fest xml file
+// the entries are read one at a time, so the size of the package
+// doesn't matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
+pub enum Entry {
+    /// OppfLegemiddelpakning
+    Package(Package),
+    /// OppfInteraksjon
+    Interaction(Interaction),
+    /// OppfVirkestoff with Virkestoff
+    ActiveSubstance(ActiveSubstance),
+    /// OppfVirkestoff with VirkestoffMedStyrke
+    SubstanceStrength(SubstanceStrength),
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Package,
+    Interaction,
+    Substance,
+}
+
+impl Kind {
+    fn from_tag(tag: &[u8]) -> Option<Self> {
+        match tag {
+            b"OppfLegemiddelpakning" => Some(Kind::Package),
+            b"OppfInteraksjon" => Some(Kind::Interaction),
+            b"OppfVirkestoff" => Some(Kind::Substance),
+            _ => None,
+        }
+    }
+}
+
+/// Pull parser that reads the entries from the fest xml file
+///
+/// # Example
+/// ```
+/// use festlib::{Entry, FestReader};
+///
+/// let reader = FestReader::from_file("test_fest.xml").unwrap();
+///
+/// let mut packages = 0;
+/// for entry in reader {
+///     if let Entry::Package(_) = entry.unwrap() {
+///         packages += 1;
+///     }
+/// }
+///
+/// assert_eq!(packages, 5);
+/// ```
+pub struct FestReader<R: BufRead> {
+    reader: Reader<Position<R>>,
+    buf: Vec<u8>,
+    namespaces: Vec<(Vec<u8>, Vec<u8>)>,
+    schema_version: SchemaVersion,
+    delivery_date: Option<String>,
+    in_delivery_date: bool,
+    depth: usize,
+    after_text: bool,
+    done: bool,
+    report: ParseReport,
+}
+
+impl FestReader<BufReader<File>> {
+    /// Opens the fest xml file for streaming
+    pub fn from_file<P: AsRef<Path>>(filename: P) -> Result<Self, FestError> {
+        Ok(FestReader::new(BufReader::new(File::open(filename)?)))
+    }
+}
+
+impl<R: BufRead> FestReader<R> {
+    /// Constructor for a streaming parser over the reader
+    pub fn new(reader: R) -> Self {
+        FestReader {
+            reader: Reader::from_reader(Position::new(reader)),
+            buf: Vec::new(),
+            namespaces: Vec::new(),
+            schema_version: SchemaVersion::default(),
+            delivery_date: None,
+            in_delivery_date: false,
+            depth: 0,
+            after_text: false,
+            done: false,
+            report: ParseReport::default(),
+        }
+    }
+
+    /// Returns the last update for the fest xml file (HentetDato).
+    /// HentetDato is at the start of the file, so it is known when
+    /// the first entry has been read.
+    pub fn delivery_date(&self) -> Option<&str> {
+        self.delivery_date.as_deref()
+    }
+
+    /// Returns the version of the fest message (M30), found from the
+    /// namespace of <FEST> when the first entry has been read
+    pub fn schema_version(&self) -> SchemaVersion {
+        self.schema_version
+    }
+
+    /// Returns the entries that were skipped so far, because the
+    /// parsers couldn't read them, as in [`Fest::report`]
+    ///
+    /// [`Fest::report`]: crate::Fest::report
+    pub fn report(&self) -> &ParseReport {
+        &self.report
+    }
+
+    fn next_entry(&mut self) -> Result<Option<Entry>, FestError> {
+        loop {
+            self.buf.clear();
+
+            // the < of the element after a text is read with the text
+            let (line, column) = self.reader.get_ref().position();
+            let position = if self.after_text { (line, column - 1) } else { (line, column) };
+
+            let event = self.reader.read_event_into(&mut self.buf).map_err(|e| error(&self.reader, e))?;
+            self.after_text = matches!(event, Event::Text(_));
+
+            match event {
+                Event::Start(e) => {
+                    self.depth += 1;
+
+                    match self.depth {
+                        1 => {
+                            self.namespaces = namespaces(&e);
+                            self.schema_version = schema_version(&e, &self.namespaces)?;
+                        }
+                        2 => self.in_delivery_date = e.local_name().as_ref() == b"HentetDato",
+                        3 => {
+                            let kind = Kind::from_tag(e.local_name().as_ref());
+                            let start = e.into_owned();
+                            self.depth -= 1;
+
+                            // the entries we don't parse are skipped without
+                            // keeping them in memory
+                            let Some(kind) = kind else {
+                                self.reader.read_to_end_into(start.name(), &mut self.buf)
+                                    .map_err(|e| error(&self.reader, e))?;
+                                continue;
+                            };
+
+                            let fragment = self.fragment(start)?;
+                            if let Some(entry) = parse(kind, self.schema_version, &fragment, position, &mut self.report)? {
+                                return Ok(Some(entry));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Event::Text(e) if self.in_delivery_date => {
+                    self.delivery_date = Some(String::from_utf8(e.to_vec())?.trim().to_string());
+                }
+                Event::End(_) => {
+                    self.depth -= 1;
+                    self.in_delivery_date = false;
+                }
+                Event::Eof if self.depth > 0 => {
+                    return Err(error(&self.reader, "Unexpected end of file"));
+                }
+                Event::Eof => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+
+    // reads the whole entry into a standalone xml fragment. The namespaces
+    // of the root element are added to the entry so the prefixes are known.
+    fn fragment(&mut self, mut start: BytesStart<'static>) -> Result<String, FestError> {
+        for (key, value) in &self.namespaces {
+            let attribute = start.try_get_attribute(key.as_slice())
+                .map_err(|e| error(&self.reader, e))?;
+
+            if attribute.is_none() {
+                start.push_attribute((key.as_slice(), value.as_slice()));
+            }
+        }
+
+        let name = String::from_utf8(start.name().as_ref().to_vec())?;
+        let mut writer = Writer::new(Vec::new());
+        writer.write_event(Event::Start(start))?;
+
+        let mut depth = 1;
+        while depth > 0 {
+            self.buf.clear();
+            let event = self.reader.read_event_into(&mut self.buf)
+                .map_err(|e| error(&self.reader, e))?;
+
+            match event {
+                Event::Start(_) => depth += 1,
+                Event::End(_) => depth -= 1,
+                Event::Eof => {
+                    let message = format!("Unexpected end of file in <{}>", name);
+                    return Err(error(&self.reader, message));
+                }
+                _ => {}
+            }
+
+            writer.write_event(event)?;
+        }
+
+        Ok(String::from_utf8(writer.into_inner())?)
+    }
+}
+
+impl<R: BufRead> Iterator for FestReader<R> {
+    type Item = Result<Entry, FestError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let entry = self.next_entry().transpose();
+        if !matches!(entry, Some(Ok(_))) {
+            self.done = true;
+        }
+
+        entry
+    }
+}
+
+// the error at the current position of the reader
+fn error<R, E>(reader: &Reader<Position<R>>, source: E) -> FestError
+where
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    let (line, column) = reader.get_ref().position();
+    FestError::xml(line, column, source)
+}
+
+// keeps track of the line and column of the bytes read
+struct Position<R> {
+    inner: R,
+    line: u32,
+    column: u32,
+}
+
+impl<R> Position<R> {
+    fn new(inner: R) -> Self {
+        Position { inner, line: 1, column: 1 }
+    }
+
+    fn position(&self) -> (u32, u32) {
+        (self.line, self.column)
+    }
+}
+
+fn track(line: &mut u32, column: &mut u32, bytes: &[u8]) {
+    for b in bytes {
+        match b {
+            b'\n' => {
+                *line += 1;
+                *column = 1;
+            }
+            // continuation bytes are part of the previous character
+            b if b & 0xC0 == 0x80 => {}
+            _ => *column += 1,
+        }
+    }
+}
+
+impl<R: Read> Read for Position<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        track(&mut self.line, &mut self.column, &buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for Position<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if let Ok(buf) = self.inner.fill_buf() {
+            track(&mut self.line, &mut self.column, &buf[..amt.min(buf.len())]);
+        }
+        self.inner.consume(amt)
+    }
+}
+
+// the xmlns declarations of the element
+fn namespaces(start: &BytesStart) -> Vec<(Vec<u8>, Vec<u8>)> {
+    start
+        .attributes()
+        .flatten()
+        .filter(|a| a.key.as_ref() == b"xmlns" || a.key.as_ref().starts_with(b"xmlns:"))
+        .map(|a| (a.key.as_ref().to_vec(), a.value.to_vec()))
+        .collect()
+}
+
+// the version from the namespace of the root element, which can have a prefix
+fn schema_version(start: &BytesStart, namespaces: &[(Vec<u8>, Vec<u8>)]) -> Result<SchemaVersion, FestError> {
+    let key = match start.name().prefix() {
+        Some(prefix) => [b"xmlns:", prefix.as_ref()].concat(),
+        None => b"xmlns".to_vec(),
+    };
+    let namespace = namespaces.iter().find(|(k, _)| *k == key).map(|(_, v)| String::from_utf8_lossy(v));
+    let name = String::from_utf8_lossy(start.local_name().as_ref()).to_string();
+
+    SchemaVersion::from_root(&name, namespace.as_deref())
+}
+
+// the position of the error in the fragment is moved to where the
+// fragment starts in the file. The entries that can't be read are
+// added to the report.
+fn parse(
+    kind: Kind,
+    version: SchemaVersion,
+    fragment: &str,
+    start: (u32, u32),
+    report: &mut ParseReport,
+) -> Result<Option<Entry>, FestError> {
+    let document = roxmltree::Document::parse(fragment).map_err(|e| {
+        let position = e.pos();
+        let (line, column) = match position.row {
+            1 => (start.0, start.1 + position.col - 1),
+            row => (start.0 + row - 1, position.col),
+        };
+        FestError::xml(line, column, e)
+    })?;
+    let node = document.root_element();
+
+    let entry = match kind {
+        Kind::Package => version.package(&node).map(Entry::Package),
+        Kind::Interaction => xml::interaction(&node).map(Entry::Interaction),
+        Kind::Substance if xml::is_active_substance(&node) => xml::active_substance(&node).map(Entry::ActiveSubstance),
+        Kind::Substance => xml::substance_strength(&node).map(Entry::SubstanceStrength),
+    };
+
+    if entry.is_none() {
+        report.skipped_at(&node, start);
+    }
+
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn entries(content: &str) -> Vec<Entry> {
+        FestReader::new(content.as_bytes())
+            .collect::<Result<Vec<Entry>, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_entries() {
+        let content = fs::read_to_string("test_fest.xml").unwrap();
+        let document = xml::document(&content).unwrap();
+
+        let entries = entries(&content);
+        let packages: Vec<&Package> = entries.iter()
+            .filter_map(|e| match e { Entry::Package(p) => Some(p), _ => None })
+            .collect();
+        let owned = xml::packages(&document, &mut Default::default());
+
+        assert_eq!(packages.len(), owned.len());
+        for (p, o) in packages.iter().zip(&owned) {
+            assert_eq!(serde_json::to_string(p).unwrap(), serde_json::to_string(o).unwrap());
+        }
+
+        assert_eq!(entries.iter().filter(|e| matches!(e, Entry::Interaction(_))).count(), 1);
+        assert_eq!(entries.iter().filter(|e| matches!(e, Entry::ActiveSubstance(_))).count(), 2);
+        assert_eq!(entries.iter().filter(|e| matches!(e, Entry::SubstanceStrength(_))).count(), 2);
+    }
+
+    #[test]
+    fn test_report() {
+        let content = fs::read_to_string("test_fest.xml").unwrap();
+        let broken = content.replacen("<Varenr>061561</Varenr>", "", 1);
+
+        let mut reader = FestReader::new(broken.as_bytes());
+        let entries = reader.by_ref().collect::<Result<Vec<Entry>, _>>().unwrap();
+        let fest: crate::Fest = broken.parse().unwrap();
+
+        // the same entries are skipped as when the whole file is loaded
+        let skipped: Vec<&crate::Warning> = fest.report()
+            .warnings()
+            .iter()
+            .filter(|w| matches!(w.kind(), crate::WarningKind::SkippedEntry(_)))
+            .collect();
+        assert_eq!(reader.report().warnings().iter().collect::<Vec<_>>(), skipped);
+        assert_eq!(reader.report().len(), 1);
+
+        let packages = entries.iter().filter(|e| matches!(e, Entry::Package(_))).count();
+        assert_eq!(packages, fest.packages().len());
+    }
+
+    #[test]
+    fn test_delivery_date() {
+        let mut reader = FestReader::from_file("test_fest.xml").unwrap();
+        assert!(reader.delivery_date().is_none());
+
+        reader.next();
+        assert_eq!(reader.delivery_date(), Some("2024-09-09T14:21:28"));
+    }
+
+    #[test]
+    fn test_namespace_prefix() {
+        let content = r#"<m30:FEST xmlns:m30="http://www.kith.no/xmlstds/eresept/m30/2014-12-01">
+            <m30:HentetDato>2024-09-09T14:21:28</m30:HentetDato>
+            <m30:KatLegemiddelpakning>
+                <m30:OppfLegemiddelpakning>
+                    <m30:Id>ID_1</m30:Id>
+                    <m30:Tidspunkt>2024-04-21T00:51:31</m30:Tidspunkt>
+                    <m30:Legemiddelpakning>
+                        <m30:Id>ID_2</m30:Id>
+                        <m30:NavnFormStyrke>Paracet tab 500 mg</m30:NavnFormStyrke>
+                        <m30:Varenr>061561</m30:Varenr>
+                    </m30:Legemiddelpakning>
+                </m30:OppfLegemiddelpakning>
+            </m30:KatLegemiddelpakning>
+        </m30:FEST>"#;
+
+        let entries = entries(content);
+        assert_eq!(entries.len(), 1);
+        match &entries[0] {
+            Entry::Package(p) => assert_eq!(p.itemnum(), "061561"),
+            _ => panic!("Expected a package"),
+        }
+    }
+
+    #[test]
+    fn test_legacy_version() {
+        let mut reader = FestReader::from_file("test_fest_v25.xml").unwrap();
+
+        match reader.next().unwrap().unwrap() {
+            Entry::Package(p) => assert_eq!(p.quantity().unwrap().to_string(), "28 stk"),
+            _ => panic!("Expected a package"),
+        }
+        assert_eq!(reader.schema_version(), SchemaVersion::V2_5);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let content = fs::read_to_string("test_fest.xml").unwrap();
+        let truncated = &content[..content.find("<Varenr>").unwrap()];
+
+        let mut reader = FestReader::new(truncated.as_bytes());
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+
+        // truncated between two entries
+        let truncated = &content[..content.find("<KatInteraksjon>").unwrap()];
+        let result: Result<Vec<Entry>, _> = FestReader::new(truncated.as_bytes()).collect();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_error_position() {
+        let content = "<FEST>\n  <HentetDato>2024-09-09T14:21:28</HentetDato>\n  <KatLegemiddelpakning>\n    <OppfLegemiddelpakning>\n      <Id>ID_1</Id>\n      <Varenr>0&6</Varenr>\n    </OppfLegemiddelpakning>\n  </KatLegemiddelpakning>\n</FEST>";
+
+        let result: Result<Vec<Entry>, _> = FestReader::new(content.as_bytes()).collect();
+        match result {
+            Err(FestError::Xml { line, .. }) => assert_eq!(line, 6),
+            _ => panic!("Expected a xml error"),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! The cheapest substitute in an exchange group (generisk bytte)
+//!
+//! At a substitution the pharmacy must offer the cheapest equivalent
+//! package in the exchange group, of the same pack size. A package with
+//! a step price (Trinnpris) is compared by the step price, other
+//! packages by AUP, the maximum retail price. The two are not compared
+//! with each other: the packages with a step price come first.
+
+use std::cmp::Ordering;
+use crate::kith::Timestamp;
+use crate::types::{Package, Price};
+
+/// The price a [`Substitute`] is compared by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PriceBasis {
+    /// The step price (Trinnpris)
+    StepPrice,
+    /// The maximum retail price (AUP)
+    RetailPrice,
+}
+
+impl PriceBasis {
+    /// Returns the price type of the basis (7453)
+    pub fn code(&self) -> &'static str {
+        match self {
+            PriceBasis::StepPrice => Price::STEP_PRICE,
+            PriceBasis::RetailPrice => Price::AUP,
+        }
+    }
+}
+
+/// A package from [`Fest::cheapest_substitute`], with the price it is
+/// ranked by
+///
+/// [`Fest::cheapest_substitute`]: crate::Fest::cheapest_substitute
+#[derive(Debug, Clone, Copy)]
+pub struct Substitute<'a> {
+    package: &'a Package,
+    price: &'a Price,
+    basis: PriceBasis,
+}
+
+impl<'a> Substitute<'a> {
+    /// Returns the package
+    pub fn package(&self) -> &'a Package {
+        self.package
+    }
+
+    /// Returns the price the package is ranked by
+    pub fn price(&self) -> &'a Price {
+        self.price
+    }
+
+    /// Returns the amount of the price, e.g. 98.30
+    pub fn amount(&self) -> f64 {
+        self.price.price().v().unwrap_or(f64::MAX)
+    }
+
+    /// Returns whether the step price or AUP is used
+    pub fn basis(&self) -> PriceBasis {
+        self.basis
+    }
+}
+
+/// Ranks the packages by the price at the date: the packages with a
+/// step price first, cheapest first, then the packages with only AUP.
+/// Packages without a step price or AUP at the date are left out.
+pub(crate) fn rank<'a, I>(packages: I, date: &Timestamp) -> Vec<Substitute<'a>>
+where
+    I: IntoIterator<Item = &'a Package>,
+{
+    let mut substitutes: Vec<Substitute> = packages
+        .into_iter()
+        .filter_map(|package| {
+            let (price, basis) = [PriceBasis::StepPrice, PriceBasis::RetailPrice]
+                .into_iter()
+                .find_map(|b| package.price(b.code(), date).map(|p| (p, b)))?;
+
+            price.price().v()?;
+            Some(Substitute { package, price, basis })
+        })
+        .collect();
+
+    substitutes.sort_by(|a, b| {
+        a.basis
+            .cmp(&b.basis)
+            .then_with(|| a.amount().partial_cmp(&b.amount()).unwrap_or(Ordering::Equal))
+            .then_with(|| a.package.itemnum().cmp(b.package.itemnum()))
+    });
+
+    substitutes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xml::test_packages;
+
+    fn ranked(packages: &[Package], date: Timestamp) -> Vec<(&str, f64, PriceBasis)> {
+        rank(packages, &date)
+            .iter()
+            .map(|s| (s.package().itemnum().as_str(), s.amount(), s.basis()))
+            .collect()
+    }
+
+    #[test]
+    fn test_rank() {
+        let packages = test_packages();
+
+        // the step prices first, even if an AUP is lower
+        let result = ranked(&packages, Timestamp::from_date(2025, 6, 1));
+        assert_eq!(result, vec![
+            ("654321", 95.0, PriceBasis::StepPrice),
+            ("061561", 98.3, PriceBasis::StepPrice),
+            ("017701", 50.0, PriceBasis::RetailPrice),
+            ("123456", 85.0, PriceBasis::RetailPrice),
+            ("953335", 90.0, PriceBasis::RetailPrice),
+        ]);
+    }
+
+    #[test]
+    fn test_rank_old_prices() {
+        let packages = test_packages();
+
+        // before the step price, and before 654321 had a price
+        let result = ranked(&packages, Timestamp::from_date(2023, 6, 1));
+        assert_eq!(result.len(), 4);
+        assert_eq!(result[3], ("061561", 130.0, PriceBasis::RetailPrice));
+
+        assert!(ranked(&packages, Timestamp::from_date(2011, 1, 1)).is_empty());
+    }
+}
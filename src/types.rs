@@ -1,5 +1,6 @@
 use roxmltree::Node;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use crate::kith::{Cs, Cv, Mo, Pq, Rto, Timestamp};
 use crate::xml;
 
 // TODO: remove #[allow(dead_code)] and implement all the missing parts
@@ -7,27 +8,27 @@ use crate::xml;
 
 /// Holds the last date for when the fest was last
 /// updated. (HentetDato).
-#[derive(Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct LastUpdate {
-    update: String,
+    update: Option<String>,
 }
 
 impl LastUpdate {
-    pub fn new(date: &str) -> Self {
+    pub fn new(date: Option<&str>) -> Self {
         LastUpdate {
-            update: String::from(date),
+            update: date.map(String::from),
         }
     }
 
-    pub fn date(&self) -> &String {
-        &self.update
+    /// Returns the date, or None if the file has no HentetDato
+    pub fn date(&self) -> Option<&str> {
+        self.update.as_deref()
     }
 
 }
 
 /// Holds the id reference for generic packages/drugs
-#[allow(dead_code)]
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ExchangeGroup {
     id: String,
     valid_from: Option<String>,
@@ -36,7 +37,7 @@ pub struct ExchangeGroup {
 
 impl ExchangeGroup {
     pub fn new(node: &Node) -> Option<Self> {
-        xml::exchange_group(&node)
+        xml::exchange_group(node)
     }
 
     pub fn from(id: String, valid_from: Option<String>, valid_to: Option<String>) -> Option<Self> {
@@ -50,143 +51,259 @@ impl ExchangeGroup {
     pub fn id(self) -> String {
         self.id
     }
+
+    /// Returns the date the package is in the exchange group from
+    /// (GyldigFraDato)
+    pub fn valid_from(&self) -> Option<&String> {
+        self.valid_from.as_ref()
+    }
+
+    /// Returns the last date the package is in the exchange group
+    /// (GyldigTilDato)
+    pub fn valid_to(&self) -> Option<&String> {
+        self.valid_to.as_ref()
+    }
+
+    /// Returns true if the package is in the exchange group at the
+    /// date. Dates that are missing don't limit the period.
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::{ExchangeGroup, Timestamp};
+    ///
+    /// let group = ExchangeGroup::from("BYTTE001".to_string(), Some("2020-01-01".to_string()), Some("2024-06-30".to_string())).unwrap();
+    ///
+    /// assert!(group.is_valid(&Timestamp::from_date(2024, 6, 30)));
+    /// assert!(!group.is_valid(&Timestamp::from_date(2024, 7, 1)));
+    /// assert!(!group.is_valid(&Timestamp::from_date(2019, 12, 31)));
+    /// ```
+    pub fn is_valid(&self, date: &Timestamp) -> bool {
+        let date = date.day();
+        let parse = |d: &Option<String>| d.as_deref().and_then(Timestamp::parse).map(|t| t.day());
+
+        parse(&self.valid_from).is_none_or(|from| from <= date)
+            && parse(&self.valid_to).is_none_or(|to| date <= to)
+    }
 }
 
-/// Coded Simple Value
-/// Gives a codes value with a String with an option
-/// to give the 'v' a meaning 'dn'
+/// Holds the metadata of the xml entry
 #[allow(dead_code)]
-#[derive(Debug, Serialize)]
-pub struct Cs {
-    v: String,
-    dn: String,
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Metadata {
+    id: String,
+    time: String,
+    status: Option<Cs>,
 }
 
-impl Cs {
-    pub fn new(node: &Node, tag: &str) -> Self {
-        let (v, dn) = xml::cs(node, tag);
+impl Metadata {
+    /// Returns None if the entry has no id or time
+    pub fn new(node: &Node) -> Option<Self> {
+        let (id, time) = xml::metadata(node)?;
+        let status = Cs::new(node, "Status");
 
-        Cs {
-            v,
-            dn,
-        }
+        Some(Metadata {
+            id,
+            time,
+            status,
+        })
+    }
+
+    pub fn id(&self) -> &String {
+        &self.id
     }
 
-    pub fn v(&self) -> &String {
-        &self.v
+    pub fn time(&self) -> &String {
+        &self.time
     }
 
-    pub fn dn(&self) -> &String {
-        &self.dn
+    pub fn status(&self) -> Option<&Cs> {
+        self.status.as_ref()
     }
 }
 
-/// Coded Value with a OID (object identifier)
-/// s = oid.
-/// the oid have a constant value but the last part
-/// is the identifier
-#[allow(dead_code)]
-#[derive(Debug, Serialize)]
-pub struct Cv {
-    v: String,
-    s: String,
-    dn: String,
+/// Holds when the package is on the market (Markedsforingsinfo)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarketingInfo {
+    marketing_date: Option<Timestamp>,
+    deregistration_date: Option<Timestamp>,
+    unavailable_date: Option<Timestamp>,
 }
 
-impl Cv {
-    pub fn new(node: &Node, tag: &str) -> Self {
-        let (v, s, dn) = xml::cv(node, tag);
+impl MarketingInfo {
+    pub fn new(node: &Node) -> Option<Self> {
+        xml::marketing_info(node)
+    }
 
-        Cv {
-            v,
-            s,
-            dn,
+    pub fn from(
+        marketing_date: Option<Timestamp>,
+        deregistration_date: Option<Timestamp>,
+        unavailable_date: Option<Timestamp>) -> Self {
+        MarketingInfo {
+            marketing_date,
+            deregistration_date,
+            unavailable_date,
         }
     }
 
-    pub fn v(&self) -> &String {
-        &self.v
+    /// Returns the date the package first came on the market
+    /// (Markedsforingsdato)
+    pub fn marketing_date(&self) -> Option<&Timestamp> {
+        self.marketing_date.as_ref()
+    }
+
+    /// Returns the date the package is deregistered (AvregDato)
+    pub fn deregistration_date(&self) -> Option<&Timestamp> {
+        self.deregistration_date.as_ref()
     }
 
-    pub fn s(&self) -> &String {
-        &self.s
+    /// Returns the date the package is temporarily off the market
+    /// (MidlUtgattDato)
+    pub fn unavailable_date(&self) -> Option<&Timestamp> {
+        self.unavailable_date.as_ref()
     }
 
-    pub fn dn(&self) -> &String {
-        &self.dn
+    /// Returns true if the package is on the market at the date: it
+    /// came on the market before or on the date, and isn't deregistered
+    /// or temporarily off the market by then.
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::{MarketingInfo, Timestamp};
+    ///
+    /// let info = MarketingInfo::from(Timestamp::parse("2020-01-01"), Timestamp::parse("2024-06-01"), None);
+    ///
+    /// assert!(info.is_marketed(&Timestamp::from_date(2024, 1, 1)));
+    /// assert!(!info.is_marketed(&Timestamp::from_date(2019, 1, 1)));
+    /// assert!(!info.is_marketed(&Timestamp::from_date(2024, 6, 1)));
+    /// ```
+    pub fn is_marketed(&self, date: &Timestamp) -> bool {
+        let date = date.day();
+        let before = |d: &Option<Timestamp>| d.is_some_and(|d| d.day() <= date);
+
+        before(&self.marketing_date) && !before(&self.deregistration_date) && !before(&self.unavailable_date)
     }
 }
 
-/// Holds the metadata of the xml entry
-#[allow(dead_code)]
-#[derive(Debug, Serialize)]
-pub struct Metadata {
-    id: String,
-    time: String,
-    status: Cs,
+/// Holds a price of the package (PrisVare) and the period it applies
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Price {
+    kind: Cv,
+    price: Mo,
+    valid_from: Option<Timestamp>,
+    valid_to: Option<Timestamp>,
 }
 
-impl Metadata {
-    pub fn new(node: &Node) -> Self {
-        let (id, time) = xml::metadata(node);
-        let status = Cs::new(&node, "Status");
+impl Price {
+    /// Price type for AUP, the maximum retail price (7453)
+    pub const AUP: &'static str = "3";
+    /// Price type for the step price, Trinnpris (7453)
+    pub const STEP_PRICE: &'static str = "5";
 
-        Metadata {
-            id,
-            time,
-            status,
+    pub fn new(node: &Node) -> Option<Self> {
+        xml::price(node)
+    }
+
+    pub fn from(kind: Cv, price: Mo, valid_from: Option<Timestamp>, valid_to: Option<Timestamp>) -> Self {
+        Price {
+            kind,
+            price,
+            valid_from,
+            valid_to,
         }
     }
 
-    pub fn id(&self) -> &String {
-        &self.id
+    /// Returns the price type (Type), e.g. 3 AUP or 5 Trinnpris
+    pub fn kind(&self) -> &Cv {
+        &self.kind
     }
 
-    pub fn time(&self) -> &String {
-        &self.time
+    /// Returns the price (Pris)
+    pub fn price(&self) -> &Mo {
+        &self.price
+    }
+
+    /// Returns the date the price applies from (GyldigFraDato)
+    pub fn valid_from(&self) -> Option<&Timestamp> {
+        self.valid_from.as_ref()
     }
 
-    pub fn status(&self) -> &Cs {
-        &self.status
+    /// Returns the last date the price applies (GyldigTilDato)
+    pub fn valid_to(&self) -> Option<&Timestamp> {
+        self.valid_to.as_ref()
+    }
+
+    /// Returns true if the price applies at the date. Dates that are
+    /// missing don't limit the period.
+    ///
+    /// # Example
+    /// ```
+    /// use festlib::{Cv, Mo, Price, Timestamp};
+    ///
+    /// let kind = Cv::from("3", "2.16.578.1.12.4.1.1.7453", "AUP");
+    /// let price = Price::from(kind, Mo::from(120.5, "NOK"), Timestamp::parse("2024-01-01"), None);
+    ///
+    /// assert!(price.is_valid(&Timestamp::from_date(2024, 1, 1)));
+    /// assert!(!price.is_valid(&Timestamp::from_date(2023, 12, 31)));
+    /// ```
+    pub fn is_valid(&self, date: &Timestamp) -> bool {
+        let date = date.day();
+
+        self.valid_from.is_none_or(|from| from.day() <= date)
+            && self.valid_to.is_none_or(|to| date <= to.day())
     }
 }
 
 /// Holds the information about the drug package (Legemiddelpakning).
 #[allow(dead_code)]
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Package {
     metadata: Metadata,
-    atc: Cv,
+    atc: Option<Cv>,
     name: String,
-    group: Cs,
+    group: Option<Cs>,
+    #[serde(default)]
+    form: Option<Cv>,
     id: String,
     itemnum: String,
-    ean: String,
+    ean: Option<String>,
     exchange_group: Option<ExchangeGroup>,
+    quantity: Option<Pq>,
+    ddd: Option<Pq>,
+    #[serde(default)]
+    marketing: Option<MarketingInfo>,
+    #[serde(default)]
+    prices: Vec<Price>,
 }
 
 impl Package {
+    #[allow(clippy::too_many_arguments)]
     pub fn from(
         metadata: Metadata,
-        atc: Cv,
+        atc: Option<Cv>,
         name: String,
-        group: Cs,
+        group: Option<Cs>,
+        form: Option<Cv>,
         id: String,
         itemnum: String,
-        ean: String,
-        exchange_group: Option<ExchangeGroup>) -> Option<Self> {
+        ean: Option<String>,
+        exchange_group: Option<ExchangeGroup>,
+        quantity: Option<Pq>,
+        ddd: Option<Pq>,
+        marketing: Option<MarketingInfo>,
+        prices: Vec<Price>) -> Option<Self> {
         Some(Package {
-            metadata, atc, name, group, id, itemnum, ean, exchange_group
+            metadata, atc, name, group, form, id, itemnum, ean, exchange_group,
+            quantity, ddd, marketing, prices
         })
     }
 
     pub fn new(node: &Node) -> Option<Self> {
-        xml::package(&node)
+        xml::package(node)
     }
 
-    /// Returns the EAN code for the package
-    pub fn ean(&self) -> &String {
-        &self.ean
+    /// Returns the EAN code for the package, if it has one
+    pub fn ean(&self) -> Option<&String> {
+        self.ean.as_ref()
     }
 
     /// Returns the itemnumber (varenr) for the package
@@ -196,8 +313,8 @@ impl Package {
 
     /// Return the ATC (Anatomical Therapeutic Chemical)
     /// code for the package
-    pub fn atc(&self) -> &Cv {
-        &self.atc
+    pub fn atc(&self) -> Option<&Cv> {
+        self.atc.as_ref()
     }
 
     /// Returns the unique id of the entry
@@ -211,8 +328,13 @@ impl Package {
     }
 
     /// Returns the prescription group
-    pub fn group(&self) -> &Cs {
-        &self.group
+    pub fn group(&self) -> Option<&Cs> {
+        self.group.as_ref()
+    }
+
+    /// Returns the dosage form (LegemiddelformKort), e.g. 32 Kapsel
+    pub fn form(&self) -> Option<&Cv> {
+        self.form.as_ref()
     }
 
     /// Returns the metadata for the entry
@@ -234,19 +356,140 @@ impl Package {
             None => None,
         }
     }
+
+    /// Returns the amount in the package (Mengde), e.g. 100 ml
+    pub fn quantity(&self) -> Option<&Pq> {
+        self.quantity.as_ref()
+    }
+
+    /// Returns the defined daily dose (DDD) for the package
+    pub fn ddd(&self) -> Option<&Pq> {
+        self.ddd.as_ref()
+    }
+
+    /// Returns when the package is on the market (Markedsforingsinfo)
+    pub fn marketing(&self) -> Option<&MarketingInfo> {
+        self.marketing.as_ref()
+    }
+
+    /// Returns true if the package is on the market at the date, see
+    /// [`MarketingInfo::is_marketed`]. Packages without a marketing
+    /// date are not.
+    pub fn is_marketed(&self, date: &Timestamp) -> bool {
+        self.marketing.as_ref().is_some_and(|m| m.is_marketed(date))
+    }
+
+    /// Returns the prices of the package (PrisVare)
+    pub fn prices(&self) -> &[Price] {
+        &self.prices
+    }
+
+    /// Returns the price of the type that applies at the date, e.g.
+    /// [`Price::AUP`]. If more than one applies, the one that applies
+    /// from the latest date is used.
+    pub fn price(&self, kind: &str, date: &Timestamp) -> Option<&Price> {
+        self.prices
+            .iter()
+            .filter(|p| p.kind().v() == Some(kind) && p.is_valid(date))
+            .max_by_key(|p| p.valid_from().map(Timestamp::day))
+    }
+}
+
+/// Holds an active substance (Virkestoff)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ActiveSubstance {
+    metadata: Metadata,
+    id: String,
+    name: String,
+}
+
+impl ActiveSubstance {
+    pub fn from(metadata: Metadata, id: String, name: String) -> Self {
+        ActiveSubstance {
+            metadata, id, name
+        }
+    }
+
+    pub fn new(node: &Node) -> Option<Self> {
+        xml::active_substance(node)
+    }
+
+    /// Unique id of the substance, which RefVirkestoff refers to
+    pub fn id(&self) -> &String {
+        &self.id
+    }
+
+    /// The name of the substance (Navn)
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    /// Returns the metadata for the entry
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+}
+
+/// Holds the strength of an active substance (VirkestoffMedStyrke)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubstanceStrength {
+    metadata: Metadata,
+    id: String,
+    strength: Rto,
+    substance: String,
+    atc: Option<Cv>,
+}
+
+impl SubstanceStrength {
+    pub fn from(metadata: Metadata, id: String, strength: Rto,
+        substance: String, atc: Option<Cv>) -> Self {
+        SubstanceStrength {
+            metadata, id, strength, substance, atc
+        }
+    }
+
+    pub fn new(node: &Node) -> Option<Self> {
+        xml::substance_strength(node)
+    }
+
+    /// Unique id of the substance with strength
+    pub fn id(&self) -> &String {
+        &self.id
+    }
+
+    /// The strength, e.g. 500 mg or 10 mg/ml
+    pub fn strength(&self) -> &Rto {
+        &self.strength
+    }
+
+    /// Reference to the id of the active substance (RefVirkestoff)
+    pub fn substance(&self) -> &String {
+        &self.substance
+    }
+
+    /// ATC code for the substance if its part of a combination
+    /// product (AtcKombipreparat)
+    pub fn atc(&self) -> Option<&Cv> {
+        self.atc.as_ref()
+    }
+
+    /// Returns the metadata for the entry
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
 }
 
 
 /// Part of Interaction. Is the Substance
 /// that interacts with other substances
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Substance {
     name: String,
-    atc: Cv,
+    atc: Option<Cv>,
 }
 
 impl Substance {
-    pub fn new(name: String, atc: Cv) -> Self {
+    pub fn new(name: String, atc: Option<Cv>) -> Self {
         Substance {
             name,
             atc,
@@ -259,32 +502,33 @@ impl Substance {
     }
 
     /// Atc code the interaction applies to
-    pub fn atc(&self) -> &String {
-        &self.atc.v
+    pub fn atc(&self) -> Option<&str> {
+        self.atc.as_ref().and_then(|a| a.v())
     }
 }
 
 /// Holds the information about an Interaction between two or more 
 /// Packages (substances).
 #[allow(dead_code)]
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Interaction {
     metadata: Metadata,
     id: String,
-    relevance: Cs,
-    consequence: String,
-    mechanism: String,
-    basis: Cs,
-    handling: String,
+    relevance: Option<Cs>,
+    consequence: Option<String>,
+    mechanism: Option<String>,
+    basis: Option<Cs>,
+    handling: Option<String>,
     //Visningsregler: <Vec<Cv>,
     //references: Vec<Reference>,
     substances: Vec<Substance>,
 }
 
 impl Interaction {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(metadata: Metadata, id: String,
-        relevance: Cs, consequence: String,
-        mechanism: String, basis: Cs, handling: String,
+        relevance: Option<Cs>, consequence: Option<String>,
+        mechanism: Option<String>, basis: Option<Cs>, handling: Option<String>,
         substances: Vec<Substance>) -> Self {
         Interaction {
             metadata, id, relevance, consequence,
@@ -293,7 +537,7 @@ impl Interaction {
     }
 
     /// Substances the interaction applies to
-    pub fn substances(&self) -> &Vec<Substance> {
+    pub fn substances(&self) -> &[Substance] {
         &self.substances
     }
 
@@ -301,12 +545,16 @@ impl Interaction {
     pub fn id(&self) -> &String {
         &self.id
     }
+
+    /// Returns the metadata of the entry (Oppf id, time and status)
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::Fest;
 
     // Helper function to find first OppfLegemiddelpakning node
     fn find_first_package_node<'a>(doc: &'a roxmltree::Document) -> Option<roxmltree::Node<'a, 'a>> {
@@ -319,13 +567,11 @@ mod tests {
 
     #[test]
     fn test_metadata() {
-        let fest = Fest::new("test_fest.xml").unwrap();
-
-        let content = fest.content;
+        let content = std::fs::read_to_string("test_fest.xml").unwrap();
         let content = roxmltree::Document::parse(&content[0..]).unwrap();
 
         if let Some(node) = find_first_package_node(&content) {
-            let metadata = Metadata::new(&node);
+            let metadata = Metadata::new(&node).unwrap();
 
             assert_eq!(metadata.id, "ID_F994748F-3A21-4FC3-9964-DBE097924A75");
             assert_eq!(metadata.time, "2024-04-21T00:51:31");
@@ -336,15 +582,13 @@ mod tests {
 
     #[test]
     fn test_cs() {
-        let fest = Fest::new("test_fest.xml").unwrap();
-
-        let content = fest.content;
+        let content = std::fs::read_to_string("test_fest.xml").unwrap();
         let content = roxmltree::Document::parse(&content[0..]).unwrap();
 
         if let Some(node) = find_first_package_node(&content) {
-            let metadata = Metadata::new(&node);
-            let cs = metadata.status;
-            assert_eq!(cs.v, "A");
+            let metadata = Metadata::new(&node).unwrap();
+            let cs = metadata.status.unwrap();
+            assert_eq!(cs.v(), Some("A"));
         } else {
             panic!("Could not find package node");
         }
@@ -352,19 +596,17 @@ mod tests {
 
     #[test]
     fn test_cv() {
-        let fest = Fest::new("test_fest.xml").unwrap();
-
-        let content = fest.content;
+        let content = std::fs::read_to_string("test_fest.xml").unwrap();
         let content = roxmltree::Document::parse(&content[0..]).unwrap();
 
         if let Some(node) = find_first_package_node(&content) {
             // Navigate to the Legemiddelpakning child
             for child in node.children() {
                 if child.has_tag_name("Legemiddelpakning") {
-                    let cv = Cv::new(&child, "LegemiddelformKort");
-                    assert_eq!(cv.v, "32");
-                    assert_eq!(cv.s, "2.16.578.1.12.4.1.1.7448");
-                    assert_eq!(cv.dn, "Kapsel");
+                    let cv = Cv::new(&child, "LegemiddelformKort").unwrap();
+                    assert_eq!(cv.v(), Some("32"));
+                    assert_eq!(cv.s(), Some("2.16.578.1.12.4.1.1.7448"));
+                    assert_eq!(cv.dn(), Some("Kapsel"));
                     return;
                 }
             }
@@ -376,9 +618,7 @@ mod tests {
 
     #[test]
     fn test_package() {
-        let fest = Fest::new("test_fest.xml").unwrap();
-
-        let content = fest.content;
+        let content = std::fs::read_to_string("test_fest.xml").unwrap();
         let content = roxmltree::Document::parse(&content[0..]).unwrap();
 
         if let Some(node) = find_first_package_node(&content) {
@@ -386,7 +626,15 @@ mod tests {
 
             assert_eq!(package.id, "ID_0138BA04-7B67-4FB5-B44D-7491336CAF20");
             assert_eq!(package.itemnum, "061561");
-            assert_eq!(package.ean, "7001234567890");
+            assert_eq!(package.ean.as_deref(), Some("7001234567890"));
+            assert_eq!(package.quantity().unwrap().to_string(), "28 stk");
+            assert_eq!(package.ddd().unwrap().to_string(), "20 mg");
+            assert_eq!(package.form().unwrap().dn(), Some("Kapsel"));
+
+            let marketing = package.marketing().unwrap();
+            assert_eq!(marketing.marketing_date(), Timestamp::parse("2010-03-01").as_ref());
+            assert!(package.is_marketed(&Timestamp::from_date(2024, 9, 9)));
+            assert!(!package.is_marketed(&Timestamp::from_date(2010, 2, 28)));
         } else {
             panic!("Could not find package node");
         }
@@ -0,0 +1,84 @@
+/// What a unit measures. Only units with the same dimension can be
+/// converted into each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Dimension {
+    Mass,
+    Volume,
+    Amount,
+}
+
+/// Returns the dimension of a unit and the factor to convert a value
+/// in that unit into the base unit of the dimension (g, l and mol).
+///
+/// FEST uses the units from coding system 7452 and 9090, where the
+/// same unit can be spelled in different ways (mcg, mikrog, µg).
+/// Units that are not in this table (IE, stk, dose etc.) can only be
+/// compared with the exact same unit.
+pub(crate) fn factor(unit: &str) -> Option<(Dimension, f64)> {
+    let factor = match unit.trim().to_lowercase().as_str() {
+        "kg" => (Dimension::Mass, 1e3),
+        "g" => (Dimension::Mass, 1.0),
+        "mg" => (Dimension::Mass, 1e-3),
+        "mcg" | "mikrog" | "µg" | "μg" | "ug" => (Dimension::Mass, 1e-6),
+        "ng" => (Dimension::Mass, 1e-9),
+        "l" => (Dimension::Volume, 1.0),
+        "dl" => (Dimension::Volume, 1e-1),
+        "cl" => (Dimension::Volume, 1e-2),
+        "ml" => (Dimension::Volume, 1e-3),
+        "mikrol" | "µl" | "μl" | "ul" => (Dimension::Volume, 1e-6),
+        "mol" => (Dimension::Amount, 1.0),
+        "mmol" => (Dimension::Amount, 1e-3),
+        "mikromol" | "µmol" | "μmol" | "umol" => (Dimension::Amount, 1e-6),
+        _ => return None,
+    };
+
+    Some(factor)
+}
+
+/// Converts a value from one unit to another. Returns None if the
+/// units can't be converted into each other.
+pub(crate) fn convert(value: f64, from: &str, to: &str) -> Option<f64> {
+    if from == to {
+        return Some(value);
+    }
+
+    let (from_dimension, from_factor) = factor(from)?;
+    let (to_dimension, to_factor) = factor(to)?;
+
+    if from_dimension != to_dimension {
+        return None;
+    }
+
+    Some(value * from_factor / to_factor)
+}
+
+/// Compares two floats with a relative tolerance, so that values that
+/// went through a unit conversion still compares equal.
+pub(crate) fn approx_eq(a: f64, b: f64) -> bool {
+    (a - b).abs() <= f64::EPSILON * 16.0 * a.abs().max(b.abs()).max(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert() {
+        assert_eq!(convert(1.0, "g", "mg"), Some(1000.0));
+        assert_eq!(convert(500.0, "mikrog", "mg"), Some(0.5));
+        assert_eq!(convert(5.0, "ml", "ml"), Some(5.0));
+        assert_eq!(convert(5.0, "IE", "IE"), Some(5.0));
+    }
+
+    #[test]
+    fn test_convert_incompatible() {
+        assert_eq!(convert(1.0, "g", "ml"), None);
+        assert_eq!(convert(1.0, "IE", "mg"), None);
+    }
+
+    #[test]
+    fn test_approx_eq() {
+        assert!(approx_eq(0.1 + 0.2, 0.3));
+        assert!(!approx_eq(0.3, 0.31));
+    }
+}
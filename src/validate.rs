@@ -0,0 +1,565 @@
+//! Validation of the fest xml file against the M30 schema
+//!
+//! The rules for the catalogs festlib reads are bundled here, with the
+//! cardinalities and code lists from the M30 message description and
+//! the datatypes from xml-doc/kith.xsd. Elements festlib doesn't know
+//! are not checked, and neither is the order of the elements.
+
+use std::fmt;
+use roxmltree::{Document, Node};
+use serde::{Deserialize, Serialize};
+use crate::kith::{self, NullFlavor, Timestamp};
+use crate::schema::SchemaVersion;
+use crate::xml;
+
+/// What is wrong with the element
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ViolationKind {
+    /// The element occurs fewer or more times than the schema allows.
+    /// A max of None is unbounded.
+    Cardinality {
+        element: String,
+        found: u32,
+        min: u32,
+        max: Option<u32>,
+    },
+    /// An element that isn't allowed in the entry
+    UnexpectedElement(String),
+    /// The code (V) is missing or not in the code list
+    InvalidCode { element: String, value: String },
+    /// The code system (S) is not an object identifier
+    InvalidOid { element: String, value: String },
+    /// The value doesn't match the datatype of the element
+    InvalidValue { element: String, value: String },
+}
+
+/// A violation of the schema in the fest file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Violation {
+    kind: ViolationKind,
+    entry_id: String,
+    line: u32,
+    column: u32,
+}
+
+impl Violation {
+    /// Returns what is wrong with the element
+    pub fn kind(&self) -> &ViolationKind {
+        &self.kind
+    }
+
+    /// Returns the id of the entry (Oppf id). Empty if the violation
+    /// is outside an entry, or the entry has no id.
+    pub fn entry_id(&self) -> &str {
+        &self.entry_id
+    }
+
+    /// Returns the line of the element in the fest file
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Returns the column of the element in the fest file
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{} ", self.line, self.column)?;
+
+        match &self.kind {
+            ViolationKind::Cardinality { element, found, min, max } => {
+                let max = max.map_or("*".to_string(), |m| m.to_string());
+                write!(f, "<{}> occurs {} times, expected {}..{}", element, found, min, max)?
+            }
+            ViolationKind::UnexpectedElement(e) => write!(f, "unexpected element <{}>", e)?,
+            ViolationKind::InvalidCode { element, value } => {
+                write!(f, "invalid code \"{}\" in <{}>", value, element)?
+            }
+            ViolationKind::InvalidOid { element, value } => {
+                write!(f, "invalid oid \"{}\" in <{}>", value, element)?
+            }
+            ViolationKind::InvalidValue { element, value } => {
+                write!(f, "invalid value \"{}\" in <{}>", value, element)?
+            }
+        }
+
+        if !self.entry_id.is_empty() {
+            write!(f, " in entry {}", self.entry_id)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The datatype of an element
+#[derive(Clone, Copy)]
+enum Type {
+    /// Character string (ST), the text of the element
+    St,
+    /// Decimal number as text, e.g. <Mengde>28</Mengde>
+    Decimal,
+    /// dateTime as text, a date with time
+    DateTime,
+    /// date as text, without time
+    Date,
+    /// Coded simple value (CS), with the allowed codes
+    Cs(&'static [&'static str]),
+    /// Coded value (CV), with the allowed codes. An empty list allows
+    /// all the codes.
+    Cv(&'static [&'static str]),
+    /// Physical quantity (PQ)
+    Pq,
+    /// Monetary amount (MO)
+    Mo,
+    /// An element with child elements
+    Element(&'static [Rule]),
+}
+
+/// An element with its datatype and cardinality
+struct Rule {
+    name: &'static str,
+    kind: Type,
+    min: u32,
+    max: Option<u32>,
+}
+
+const fn rule(name: &'static str, kind: Type, min: u32, max: Option<u32>) -> Rule {
+    Rule { name, kind, min, max }
+}
+
+/// A catalog (Kat*) with the entries (Oppf*) it contains. The entry
+/// contains one of the elements.
+struct Catalog {
+    name: &'static str,
+    entry: &'static str,
+    elements: &'static [Rule],
+    // the catalog must be in the file, as for Fest::new
+    required: bool,
+}
+
+// 7446 Oppføringsstatus
+const STATUS: &[&str] = &["A", "U"];
+
+// 7421 Reseptgruppe
+const RESEPTGRUPPE: &[&str] = &["A", "B", "C", "CF", "F", "K"];
+
+// 7483 Relevans for interaksjoner
+const RELEVANS: &[&str] = &["1", "2", "3"];
+
+// 7453 Pristype for legemidler
+const PRISTYPE: &[&str] = &["2", "3", "4", "5"];
+
+// the fields every entry has (Enkeltoppforing)
+const ENTRY: &[Rule] = &[
+    rule("Id", Type::St, 1, Some(1)),
+    rule("Tidspunkt", Type::DateTime, 1, Some(1)),
+    rule("Status", Type::Cs(STATUS), 1, Some(1)),
+];
+
+const EXCHANGE_GROUP: &[Rule] = &[
+    rule("RefByttegruppe", Type::St, 1, Some(1)),
+    rule("GyldigFraDato", Type::Date, 0, Some(1)),
+    rule("GyldigTilDato", Type::Date, 0, Some(1)),
+];
+
+const PACKAGE_INFO: &[Rule] = &[
+    rule("EnhetPakning", Type::Cv(&[]), 0, Some(1)),
+    rule("Mengde", Type::Pq, 0, Some(1)),
+    rule("DDD", Type::Pq, 0, Some(1)),
+];
+
+// in v2.5 Mengde is a decimal with the unit in EnhetPakning
+const PACKAGE_INFO_V2_5: &[Rule] = &[
+    rule("EnhetPakning", Type::Cv(&[]), 0, Some(1)),
+    rule("Mengde", Type::Decimal, 0, Some(1)),
+    rule("DDD", Type::Pq, 0, Some(1)),
+];
+
+const MARKETING_INFO: &[Rule] = &[
+    rule("VarenrUtgaende", Type::St, 0, Some(1)),
+    rule("Markedsforingsdato", Type::Date, 0, Some(1)),
+    rule("AvregDato", Type::Date, 0, Some(1)),
+    rule("MidlUtgattDato", Type::Date, 0, Some(1)),
+];
+
+const PRICE: &[Rule] = &[
+    rule("Type", Type::Cv(PRISTYPE), 1, Some(1)),
+    rule("Pris", Type::Mo, 1, Some(1)),
+    rule("GyldigFraDato", Type::Date, 0, Some(1)),
+    rule("GyldigTilDato", Type::Date, 0, Some(1)),
+];
+
+const fn package(info: &'static [Rule]) -> [Rule; 11] {
+    [
+        rule("Atc", Type::Cv(&[]), 0, Some(1)),
+        rule("NavnFormStyrke", Type::St, 1, Some(1)),
+        rule("Reseptgruppe", Type::Cs(RESEPTGRUPPE), 1, Some(1)),
+        rule("LegemiddelformKort", Type::Cv(&[]), 0, Some(1)),
+        rule("Id", Type::St, 0, Some(1)),
+        rule("Varenr", Type::St, 1, Some(1)),
+        rule("Ean", Type::St, 0, Some(1)),
+        rule("Pakningsinfo", Type::Element(info), 0, None),
+        rule("Markedsforingsinfo", Type::Element(MARKETING_INFO), 0, Some(1)),
+        rule("PakningByttegruppe", Type::Element(EXCHANGE_GROUP), 0, Some(1)),
+        rule("PrisVare", Type::Element(PRICE), 0, None),
+    ]
+}
+
+const PACKAGE: &[Rule] = &package(PACKAGE_INFO);
+const PACKAGE_V2_5: &[Rule] = &package(PACKAGE_INFO_V2_5);
+
+const SUBSTANCE: &[Rule] = &[
+    rule("Substans", Type::St, 1, Some(1)),
+    rule("Atc", Type::Cv(&[]), 0, Some(1)),
+];
+
+const SUBSTANCE_GROUP: &[Rule] = &[rule("Substans", Type::Element(SUBSTANCE), 1, None)];
+
+const INTERACTION: &[Rule] = &[
+    rule("Id", Type::St, 1, Some(1)),
+    rule("Relevans", Type::Cs(RELEVANS), 1, Some(1)),
+    rule("KliniskKonsekvens", Type::St, 1, Some(1)),
+    rule("Interaksjonsmekanisme", Type::St, 0, Some(1)),
+    rule("Kildegrunnlag", Type::Cs(&[]), 1, Some(1)),
+    rule("Handtering", Type::St, 0, Some(1)),
+    rule("Substansgruppe", Type::Element(SUBSTANCE_GROUP), 2, None),
+];
+
+const STRENGTH: &[Rule] = &[
+    rule("Id", Type::St, 1, Some(1)),
+    rule("Styrke", Type::Pq, 1, Some(1)),
+    rule("StyrkeNevner", Type::Pq, 0, Some(1)),
+    rule("RefVirkestoff", Type::St, 1, Some(1)),
+];
+
+const SUBSTANCE_ENTRY: &[Rule] = &[
+    rule("Id", Type::St, 1, Some(1)),
+    rule("Navn", Type::St, 1, Some(1)),
+];
+
+const fn packages(package: &'static [Rule]) -> Catalog {
+    Catalog {
+        name: "KatLegemiddelpakning",
+        entry: "OppfLegemiddelpakning",
+        elements: package,
+        required: true,
+    }
+}
+
+const INTERACTIONS: Catalog = Catalog {
+    name: "KatInteraksjon",
+    entry: "OppfInteraksjon",
+    elements: &[rule("Interaksjon", Type::Element(INTERACTION), 1, Some(1))],
+    required: true,
+};
+
+const SUBSTANCES: Catalog = Catalog {
+    name: "KatVirkestoff",
+    entry: "OppfVirkestoff",
+    elements: &[
+        rule("VirkestoffMedStyrke", Type::Element(STRENGTH), 1, Some(1)),
+        rule("Virkestoff", Type::Element(SUBSTANCE_ENTRY), 1, Some(1)),
+    ],
+    required: false,
+};
+
+const CATALOGS: &[Catalog] = &[
+    packages(&[rule("Legemiddelpakning", Type::Element(PACKAGE), 1, Some(1))]),
+    INTERACTIONS,
+    SUBSTANCES,
+];
+
+const CATALOGS_V2_5: &[Catalog] = &[
+    packages(&[rule("Legemiddelpakning", Type::Element(PACKAGE_V2_5), 1, Some(1))]),
+    INTERACTIONS,
+    SUBSTANCES,
+];
+
+/// Validates the document against the rules for the version
+pub(crate) fn validate(document: &Document, version: SchemaVersion) -> Vec<Violation> {
+    let mut validator = Validator {
+        document,
+        violations: Vec::new(),
+    };
+
+    let root = document.root_element();
+    validator.children(&root, "", &[rule("HentetDato", Type::DateTime, 1, Some(1))]);
+
+    let catalogs = match version {
+        SchemaVersion::V2_5 => CATALOGS_V2_5,
+        SchemaVersion::V2_5_1 => CATALOGS,
+    };
+
+    for catalog in catalogs {
+        let Some(node) = xml::catalog(document, catalog.name) else {
+            if catalog.required {
+                let kind = ViolationKind::Cardinality { element: catalog.name.to_string(), found: 0, min: 1, max: Some(1) };
+                validator.push(&root, "", kind);
+            }
+            continue;
+        };
+
+        for entry in node.children().filter(|n| n.is_element()) {
+            if entry.has_tag_name(catalog.entry) {
+                validator.entry(&entry, catalog);
+            } else {
+                validator.push(&entry, "", ViolationKind::UnexpectedElement(entry.tag_name().name().to_string()));
+            }
+        }
+    }
+
+    validator.violations
+}
+
+struct Validator<'d, 'input> {
+    document: &'d Document<'input>,
+    violations: Vec<Violation>,
+}
+
+impl Validator<'_, '_> {
+    fn push(&mut self, node: &Node, entry_id: &str, kind: ViolationKind) {
+        let position = self.document.text_pos_at(node.range().start);
+
+        self.violations.push(Violation {
+            kind,
+            entry_id: entry_id.to_string(),
+            line: position.row,
+            column: position.col,
+        });
+    }
+
+    fn entry(&mut self, node: &Node, catalog: &Catalog) {
+        let id = xml::string_value(node, "Id").unwrap_or_default();
+        self.children(node, &id, ENTRY);
+
+        for child in node.children().filter(|n| n.is_element()) {
+            let name = child.tag_name().name();
+            let known = ENTRY.iter().chain(catalog.elements).any(|r| r.name == name);
+            if !known {
+                self.push(&child, &id, ViolationKind::UnexpectedElement(name.to_string()));
+            }
+        }
+
+        // the entry contains exactly one of the elements
+        let found: Vec<(Node, &Rule)> = node
+            .children()
+            .filter_map(|n| catalog.elements.iter().find(|r| n.has_tag_name(r.name)).map(|r| (n, r)))
+            .collect();
+
+        if found.len() != 1 {
+            let names: Vec<&str> = catalog.elements.iter().map(|r| r.name).collect();
+            let kind = ViolationKind::Cardinality {
+                element: names.join("|"),
+                found: found.len() as u32,
+                min: 1,
+                max: Some(1),
+            };
+            self.push(node, &id, kind);
+        }
+
+        for (element, rule) in found {
+            self.value(&element, &id, rule);
+        }
+    }
+
+    // checks the cardinality and value of the child elements in the rules
+    fn children(&mut self, node: &Node, id: &str, rules: &[Rule]) {
+        for rule in rules {
+            let elements: Vec<Node> = node.children().filter(|n| n.has_tag_name(rule.name)).collect();
+            let found = elements.len() as u32;
+
+            if found < rule.min || rule.max.is_some_and(|max| found > max) {
+                let kind = ViolationKind::Cardinality {
+                    element: rule.name.to_string(),
+                    found,
+                    min: rule.min,
+                    max: rule.max,
+                };
+                self.push(node, id, kind);
+            }
+
+            for element in &elements {
+                self.value(element, id, rule);
+            }
+        }
+    }
+
+    // checks the element against the datatype (kith.xsd)
+    fn value(&mut self, node: &Node, id: &str, rule: &Rule) {
+        let element = || rule.name.to_string();
+        let text = node.text().unwrap_or("").trim();
+
+        let valid = match rule.kind {
+            Type::St => true,
+            Type::Decimal => text.replace(',', ".").parse::<f64>().is_ok(),
+            Type::DateTime => Timestamp::parse(text).is_some_and(|t| t.is_date_time()),
+            Type::Date => Timestamp::parse(text).is_some_and(|t| t.is_date()),
+            Type::Pq | Type::Mo => node.attribute("V").is_none_or(|v| v.trim().parse::<f64>().is_ok()),
+            Type::Cs(codes) | Type::Cv(codes) => {
+                self.code(node, id, rule.name, codes);
+                true
+            }
+            Type::Element(rules) => {
+                self.children(node, id, rules);
+                true
+            }
+        };
+
+        if !valid {
+            let value = match rule.kind {
+                Type::Pq | Type::Mo => node.attribute("V").unwrap_or(""),
+                _ => text,
+            };
+            self.push(node, id, ViolationKind::InvalidValue { element: element(), value: value.to_string() });
+        }
+    }
+
+    // a code (V) from the code list, or a NULL flavor. The code system
+    // (S) is an oid.
+    fn code(&mut self, node: &Node, id: &str, name: &str, codes: &[&str]) {
+        if let Some(null) = node.attribute("NULL") {
+            if NullFlavor::from_code(null).is_none() {
+                let kind = ViolationKind::InvalidValue { element: name.to_string(), value: null.to_string() };
+                self.push(node, id, kind);
+            }
+        } else {
+            let value = node.attribute("V").unwrap_or("").trim();
+            if value.is_empty() || (!codes.is_empty() && !codes.contains(&value)) {
+                let kind = ViolationKind::InvalidCode { element: name.to_string(), value: value.to_string() };
+                self.push(node, id, kind);
+            }
+        }
+
+        if let Some(system) = node.attribute("S") {
+            if !kith::is_oid(system) {
+                let kind = ViolationKind::InvalidOid { element: name.to_string(), value: system.to_string() };
+                self.push(node, id, kind);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const BROKEN: &str = r#"<FEST>
+    <HentetDato>2024-09-09T14:21:28</HentetDato>
+    <KatLegemiddelpakning>
+        <OppfLegemiddelpakning>
+            <Id>ID_1</Id>
+            <Tidspunkt>i går</Tidspunkt>
+            <Status V="X"/>
+            <Legemiddelpakning>
+                <Atc V="A01AA01" S="2.16.578.x"/>
+                <NavnFormStyrke>Test</NavnFormStyrke>
+                <Reseptgruppe NULL="UNK"/>
+                <Varenr>061561</Varenr>
+                <Varenr>061562</Varenr>
+                <Pakningsinfo>
+                    <Mengde V="mange" U="stk"/>
+                </Pakningsinfo>
+            </Legemiddelpakning>
+            <Ukjent/>
+        </OppfLegemiddelpakning>
+    </KatLegemiddelpakning>
+    <KatInteraksjon>
+        <OppfInteraksjon>
+            <Id>ID_2</Id>
+            <Tidspunkt>2024-04-21T01:00:00</Tidspunkt>
+            <Status V="A"/>
+            <Interaksjon>
+                <Id>ID_3</Id>
+                <Relevans V="1"/>
+                <KliniskKonsekvens>Test</KliniskKonsekvens>
+                <Kildegrunnlag V="1"/>
+                <Substansgruppe>
+                    <Substans><Substans>A</Substans></Substans>
+                </Substansgruppe>
+            </Interaksjon>
+        </OppfInteraksjon>
+    </KatInteraksjon>
+</FEST>"#;
+
+    fn kinds(content: &str) -> Vec<ViolationKind> {
+        let document = xml::document(content).unwrap();
+        let version = SchemaVersion::detect(&document).unwrap();
+
+        validate(&document, version).into_iter().map(|v| v.kind).collect()
+    }
+
+    #[test]
+    fn test_valid() {
+        for file in ["test_fest.xml", "test_fest_v25.xml"] {
+            let content = fs::read_to_string(file).unwrap();
+            assert_eq!(kinds(&content), vec![], "{}", file);
+        }
+    }
+
+    #[test]
+    fn test_violations() {
+        assert_eq!(kinds(BROKEN), vec![
+            ViolationKind::InvalidValue { element: "Tidspunkt".to_string(), value: "i går".to_string() },
+            ViolationKind::InvalidCode { element: "Status".to_string(), value: "X".to_string() },
+            ViolationKind::UnexpectedElement("Ukjent".to_string()),
+            ViolationKind::InvalidOid { element: "Atc".to_string(), value: "2.16.578.x".to_string() },
+            ViolationKind::Cardinality { element: "Varenr".to_string(), found: 2, min: 1, max: Some(1) },
+            ViolationKind::InvalidValue { element: "Mengde".to_string(), value: "mange".to_string() },
+            ViolationKind::Cardinality { element: "Substansgruppe".to_string(), found: 1, min: 2, max: None },
+        ]);
+    }
+
+    #[test]
+    fn test_missing_catalogs() {
+        let missing = |element: &str| ViolationKind::Cardinality { element: element.to_string(), found: 0, min: 1, max: Some(1) };
+
+        assert_eq!(kinds("<FEST><HentetDato>2024-09-09T14:21:28</HentetDato></FEST>"), vec![
+            missing("KatLegemiddelpakning"),
+            missing("KatInteraksjon"),
+        ]);
+    }
+
+    #[test]
+    fn test_dates() {
+        let content = fs::read_to_string("test_fest.xml").unwrap()
+            .replace("<HentetDato>2024-09-09T14:21:28</HentetDato>", "<HentetDato>2024</HentetDato>")
+            .replacen("<Tidspunkt>2024-04-21T00:51:31</Tidspunkt>", "<Tidspunkt>2024-04-21</Tidspunkt>", 1)
+            .replacen("<Markedsforingsdato>2010-03-01</Markedsforingsdato>", "<Markedsforingsdato>2010-03</Markedsforingsdato>", 1)
+            .replacen("<GyldigTilDato>2023-12-31</GyldigTilDato>", "<GyldigTilDato>2023-12-31T00:00:00</GyldigTilDato>", 1);
+        let invalid = |element: &str, value: &str| ViolationKind::InvalidValue { element: element.to_string(), value: value.to_string() };
+
+        // the dates are xs:dateTime and xs:date, not any TS
+        assert_eq!(kinds(&content), vec![
+            invalid("HentetDato", "2024"),
+            invalid("Tidspunkt", "2024-04-21"),
+            invalid("Markedsforingsdato", "2010-03"),
+            invalid("GyldigTilDato", "2023-12-31T00:00:00"),
+        ]);
+    }
+
+    #[test]
+    fn test_legacy_quantity() {
+        let content = fs::read_to_string("test_fest_v25.xml").unwrap();
+
+        let content = content.replace("<Mengde>28</Mengde>", r#"<Mengde V="28" U="stk"/>"#);
+        assert_eq!(kinds(&content), vec![
+            ViolationKind::InvalidValue { element: "Mengde".to_string(), value: "".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn test_display() {
+        let document = xml::document(BROKEN).unwrap();
+        let violations = validate(&document, SchemaVersion::V2_5_1);
+
+        assert_eq!(violations[1].to_string(), "7:13 invalid code \"X\" in <Status> in entry ID_1");
+        assert_eq!(
+            violations[6].to_string(),
+            "26:13 <Substansgruppe> occurs 1 times, expected 2..* in entry ID_2"
+        );
+    }
+}
@@ -0,0 +1,395 @@
+//! Borrowed views over the fest xml
+//!
+//! [`FestView`] reads the entries straight from the parsed document
+//! without copying the values into owned strings. The text values are
+//! borrowed from the document, and from the source buffer when the text
+//! has no escapes. roxmltree keeps the unescaped text of values with
+//! entity or character references (`&amp;`, `&#248;`) itself, so the
+//! memory used besides the xml is the node tree and those values.
+
+use roxmltree::{Document, Node};
+use crate::error::FestError;
+use crate::schema::SchemaVersion;
+use crate::types::{Interaction, Package};
+use crate::xml;
+
+/// Extracts the text of the child element, borrowed from the document
+fn text<'a>(node: &Node<'a, '_>, tag: &str) -> Option<&'a str> {
+    node.children()
+        .find(|n| n.has_tag_name(tag))
+        .map(|n| n.text().unwrap_or(""))
+}
+
+fn child<'a, 'input>(node: &Node<'a, 'input>, tag: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(tag))
+}
+
+/// Borrowed coded value (CS or CV)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeRef<'a> {
+    v: Option<&'a str>,
+    s: Option<&'a str>,
+    dn: Option<&'a str>,
+}
+
+impl<'a> CodeRef<'a> {
+    fn new(node: &Node<'a, '_>, tag: &str) -> Option<Self> {
+        let node = child(node, tag)?;
+
+        Some(CodeRef {
+            v: node.attribute("V"),
+            s: node.attribute("S"),
+            dn: node.attribute("DN"),
+        })
+    }
+
+    /// Returns the code (V)
+    pub fn v(&self) -> Option<&'a str> {
+        self.v
+    }
+
+    /// Returns the code system (S). Only set for CV values
+    pub fn s(&self) -> Option<&'a str> {
+        self.s
+    }
+
+    /// Returns the display name (DN)
+    pub fn dn(&self) -> Option<&'a str> {
+        self.dn
+    }
+}
+
+/// Borrowed drug package (OppfLegemiddelpakning)
+#[derive(Debug, Clone, Copy)]
+pub struct PackageRef<'a> {
+    node: Node<'a, 'a>,
+    entry_id: &'a str,
+    status: Option<CodeRef<'a>>,
+    atc: Option<CodeRef<'a>>,
+    name: &'a str,
+    group: Option<CodeRef<'a>>,
+    id: &'a str,
+    itemnum: &'a str,
+    ean: Option<&'a str>,
+    exchange_id: Option<&'a str>,
+    version: SchemaVersion,
+}
+
+impl<'a> PackageRef<'a> {
+    fn new(node: Node<'a, 'a>, version: SchemaVersion) -> Option<Self> {
+        let package = child(&node, "Legemiddelpakning")?;
+        let exchange_id = child(&package, "PakningByttegruppe")
+            .and_then(|n| text(&n, "RefByttegruppe"))
+            .filter(|id| !id.is_empty());
+
+        Some(PackageRef {
+            node,
+            entry_id: text(&node, "Id").unwrap_or(""),
+            status: CodeRef::new(&node, "Status"),
+            atc: CodeRef::new(&package, "Atc"),
+            name: text(&package, "NavnFormStyrke").unwrap_or(""),
+            group: CodeRef::new(&package, "Reseptgruppe"),
+            id: text(&package, "Id").unwrap_or(""),
+            itemnum: text(&package, "Varenr").unwrap_or(""),
+            ean: text(&package, "Ean").filter(|ean| !ean.is_empty()),
+            exchange_id,
+            version,
+        })
+    }
+
+    /// Returns the id of the entry (Oppf id)
+    pub fn entry_id(&self) -> &'a str {
+        self.entry_id
+    }
+
+    /// Returns the status of the entry
+    pub fn status(&self) -> Option<CodeRef<'a>> {
+        self.status
+    }
+
+    /// Returns the ATC code for the package
+    pub fn atc(&self) -> Option<CodeRef<'a>> {
+        self.atc
+    }
+
+    /// Returns the name of the package
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Returns the prescription group
+    pub fn group(&self) -> Option<CodeRef<'a>> {
+        self.group
+    }
+
+    /// Returns the unique id of the package
+    pub fn id(&self) -> &'a str {
+        self.id
+    }
+
+    /// Returns the itemnumber (varenr) for the package
+    pub fn itemnum(&self) -> &'a str {
+        self.itemnum
+    }
+
+    /// Returns the EAN code for the package, if it has one
+    pub fn ean(&self) -> Option<&'a str> {
+        self.ean
+    }
+
+    /// Returns the exchange id reference for generic products
+    pub fn exchange_id(&self) -> Option<&'a str> {
+        self.exchange_id
+    }
+
+    /// Copies the package into an owned Package
+    pub fn to_package(&self) -> Option<Package> {
+        self.version.package(&self.node)
+    }
+}
+
+/// Borrowed substance in an interaction (Substans)
+#[derive(Debug, Clone, Copy)]
+pub struct SubstanceRef<'a> {
+    name: &'a str,
+    atc: Option<CodeRef<'a>>,
+}
+
+impl<'a> SubstanceRef<'a> {
+    /// Returns the name of the substance
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Returns the ATC code of the substance
+    pub fn atc(&self) -> Option<&'a str> {
+        self.atc?.v()
+    }
+}
+
+/// Borrowed interaction (OppfInteraksjon)
+#[derive(Debug, Clone, Copy)]
+pub struct InteractionRef<'a> {
+    node: Node<'a, 'a>,
+    interaction: Node<'a, 'a>,
+    entry_id: &'a str,
+    id: &'a str,
+    relevance: Option<CodeRef<'a>>,
+    consequence: Option<&'a str>,
+    mechanism: Option<&'a str>,
+    handling: Option<&'a str>,
+}
+
+impl<'a> InteractionRef<'a> {
+    fn new(node: Node<'a, 'a>) -> Option<Self> {
+        let interaction = child(&node, "Interaksjon")?;
+
+        Some(InteractionRef {
+            node,
+            interaction,
+            entry_id: text(&node, "Id").unwrap_or(""),
+            id: text(&interaction, "Id").unwrap_or(""),
+            relevance: CodeRef::new(&interaction, "Relevans"),
+            consequence: text(&interaction, "KliniskKonsekvens").filter(|t| !t.is_empty()),
+            mechanism: text(&interaction, "Interaksjonsmekanisme").filter(|t| !t.is_empty()),
+            handling: text(&interaction, "Handtering").filter(|t| !t.is_empty()),
+        })
+    }
+
+    /// Returns the id of the entry (Oppf id)
+    pub fn entry_id(&self) -> &'a str {
+        self.entry_id
+    }
+
+    /// Returns the id of the interaction
+    pub fn id(&self) -> &'a str {
+        self.id
+    }
+
+    /// Returns the clinical relevance (Relevans)
+    pub fn relevance(&self) -> Option<CodeRef<'a>> {
+        self.relevance
+    }
+
+    /// Returns the clinical consequence (KliniskKonsekvens)
+    pub fn consequence(&self) -> Option<&'a str> {
+        self.consequence
+    }
+
+    /// Returns the interaction mechanism (Interaksjonsmekanisme)
+    pub fn mechanism(&self) -> Option<&'a str> {
+        self.mechanism
+    }
+
+    /// Returns how the interaction should be handled (Handtering)
+    pub fn handling(&self) -> Option<&'a str> {
+        self.handling
+    }
+
+    /// Returns the substances that interacts
+    pub fn substances(&self) -> impl Iterator<Item = SubstanceRef<'a>> {
+        self.interaction
+            .children()
+            .filter(|x| x.has_tag_name("Substansgruppe"))
+            .flat_map(|x| x.children())
+            .filter(|s| s.has_tag_name("Substans"))
+            .map(|s| SubstanceRef {
+                name: text(&s, "Substans").unwrap_or(""),
+                atc: CodeRef::new(&s, "Atc"),
+            })
+    }
+
+    /// Copies the interaction into an owned Interaction
+    pub fn to_interaction(&self) -> Option<Interaction> {
+        xml::interaction(&self.node)
+    }
+}
+
+/// Borrowed view over the fest xml
+///
+/// # Example
+/// ```
+/// use festlib::FestView;
+///
+/// let content = std::fs::read_to_string("test_fest.xml").unwrap();
+/// let fest = FestView::new(&content).unwrap();
+///
+/// let package = fest.find_package("061561").unwrap();
+/// assert_eq!(package.ean(), Some("7001234567890"));
+/// ```
+pub struct FestView<'a> {
+    document: Document<'a>,
+    schema_version: SchemaVersion,
+}
+
+impl<'a> FestView<'a> {
+    /// Parses the content of the fest xml file
+    pub fn new(content: &'a str) -> Result<Self, FestError> {
+        let document = xml::document(content)?;
+        let schema_version = SchemaVersion::detect(&document)?;
+
+        Ok(FestView { document, schema_version })
+    }
+
+    /// Returns the version of the fest message (M30)
+    pub fn schema_version(&self) -> SchemaVersion {
+        self.schema_version
+    }
+
+    /// Retrieve the last update for the fest xml file (HentetDato)
+    pub fn delivery_date(&self) -> Option<&str> {
+        text(&self.document.root_element(), "HentetDato")
+    }
+
+    fn entries(&self, catalog: &'static str, entry: &'static str) -> impl Iterator<Item = Node<'_, 'a>> {
+        self.document
+            .root_element()
+            .children()
+            .find(move |n| n.has_tag_name(catalog))
+            .into_iter()
+            .flat_map(|n| n.children())
+            .filter(move |x| x.has_tag_name(entry))
+    }
+
+    /// Iterates over all drug packages (OppfLegemiddelpakning)
+    pub fn packages(&self) -> impl Iterator<Item = PackageRef<'_>> {
+        let version = self.schema_version;
+        self.entries("KatLegemiddelpakning", "OppfLegemiddelpakning")
+            .filter_map(move |n| PackageRef::new(n, version))
+    }
+
+    /// Iterates over all interactions (OppfInteraksjon)
+    pub fn interactions(&self) -> impl Iterator<Item = InteractionRef<'_>> {
+        self.entries("KatInteraksjon", "OppfInteraksjon")
+            .filter_map(InteractionRef::new)
+    }
+
+    /// Search for a package with itemnumber
+    pub fn find_package(&self, itemnum: &str) -> Option<PackageRef<'_>> {
+        self.packages().find(|p| p.itemnum() == itemnum)
+    }
+
+    /// Search for generic products of a package
+    pub fn find_generic<'s>(&'s self, package: &PackageRef) -> impl Iterator<Item = PackageRef<'s>> {
+        let exchange_id = package.exchange_id().map(String::from);
+
+        self.packages()
+            .filter(move |p| exchange_id.is_some() && p.exchange_id() == exchange_id.as_deref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn file_content() -> String {
+        fs::read_to_string("test_fest.xml").unwrap()
+    }
+
+    #[test]
+    fn test_escaped_text() {
+        let content = file_content().replacen("Test Medicine 10mg", "Test &amp; Medisin &#248; 10mg", 1);
+        let fest = FestView::new(&content).unwrap();
+
+        let package = fest.packages().next().unwrap();
+        assert_eq!(package.name(), "Test & Medisin ø 10mg Kapsel");
+    }
+
+    #[test]
+    fn test_packages() {
+        let content = file_content();
+        let fest = FestView::new(&content).unwrap();
+
+        assert_eq!(fest.delivery_date(), Some("2024-09-09T14:21:28"));
+        assert_eq!(fest.packages().count(), 5);
+
+        let package = fest.find_package("061561").unwrap();
+        assert_eq!(package.entry_id(), "ID_F994748F-3A21-4FC3-9964-DBE097924A75");
+        assert_eq!(package.id(), "ID_0138BA04-7B67-4FB5-B44D-7491336CAF20");
+        assert_eq!(package.status().unwrap().v(), Some("A"));
+        assert_eq!(fest.find_generic(&package).count(), 4);
+    }
+
+    #[test]
+    fn test_borrowed_from_source() {
+        let content = file_content();
+        let fest = FestView::new(&content).unwrap();
+
+        let range = content.as_ptr() as usize..content.as_ptr() as usize + content.len();
+        let package = fest.find_package("061561").unwrap();
+        assert!(range.contains(&(package.itemnum().as_ptr() as usize)));
+        assert!(range.contains(&(package.name().as_ptr() as usize)));
+    }
+
+    #[test]
+    fn test_to_owned() {
+        let content = file_content();
+        let fest = FestView::new(&content).unwrap();
+        let document = xml::document(&content).unwrap();
+
+        let owned = xml::packages(&document, &mut Default::default());
+        let borrowed: Vec<Package> = fest.packages().filter_map(|p| p.to_package()).collect();
+        assert_eq!(owned.len(), borrowed.len());
+        assert_eq!(owned[0].ean(), borrowed[0].ean());
+
+        let interaction = fest.interactions().next().unwrap();
+        assert_eq!(interaction.substances().count(), 2);
+        assert_eq!(interaction.to_interaction().unwrap().id(), interaction.id());
+    }
+
+    #[test]
+    fn test_legacy_version() {
+        let content = fs::read_to_string("test_fest_v25.xml").unwrap();
+        let fest = FestView::new(&content).unwrap();
+
+        assert_eq!(fest.schema_version(), SchemaVersion::V2_5);
+        let package = fest.find_package("017701").unwrap().to_package().unwrap();
+        assert_eq!(package.quantity().unwrap().to_string(), "100.5 ml");
+    }
+
+    #[test]
+    fn test_invalid_xml() {
+        assert!(FestView::new("<FEST><KatLegemiddelpakning>").is_err());
+    }
+}
@@ -1,44 +1,39 @@
-use crate::types::{Cs, Cv, ExchangeGroup, Metadata, Package, Interaction, Substance};
+use crate::catalog::Catalog;
+use crate::error::FestError;
+use crate::kith::{self, Cs, Cv, Mo, Pq, Rto, St, Timestamp};
+use crate::report::ParseReport;
+use crate::types::{ActiveSubstance, ExchangeGroup, MarketingInfo, Metadata, Package, Price, Interaction, Substance, SubstanceStrength};
 use roxmltree::{Document, Node};
 
 /// Parses the content string into a roxmltree::Document
-pub(crate) fn document(content: &str) -> Document<'_> {
-    // panic if invalid xml
-    roxmltree::Document::parse(&content[0..]).unwrap()   
+pub(crate) fn document(content: &str) -> Result<Document<'_>, FestError> {
+    Ok(roxmltree::Document::parse(content)?)
 }
 
-/// Extract a Coded Simple Value from xml
-pub(crate) fn cs(node: &Node, tag: &str) -> (String, String) {
-    node.children()
-        .find(|n| n.has_tag_name(tag))
-        .map(|n| {
-            let v = n.attribute("V").unwrap_or("").to_string();
-            let dn = n.attribute("DN").unwrap_or("").to_string();
-            (v, dn)
-        })
-        .unwrap_or_default()
+/// Checks that the document has the required catalogs
+pub(crate) fn check(document: &Document, catalogs: &[&str]) -> Result<(), FestError> {
+    match catalogs.iter().find(|c| catalog(document, c).is_none()) {
+        Some(c) => Err(FestError::MissingCatalog(c.to_string())),
+        None => Ok(()),
+    }
 }
 
-/// Extract a Coded Value from xml
-pub(crate) fn cv(node: &Node, tag: &str) -> (String, String, String) {
-    node.children()
-        .find(|n| n.has_tag_name(tag))
-        .map(|n| {
-            let v = n.attribute("V").unwrap_or("").to_string();
-            let s = n.attribute("S").unwrap_or("").to_string();
-            let dn = n.attribute("DN").unwrap_or("").to_string();
-            (v, s, dn)
-        })
-        .unwrap_or_default()
+/// Retrieves the catalog (Kat*) from the document. The catalog must
+/// be in the same namespace as <FEST>. The elements inside the entries
+/// are matched on the name only, since they are in other namespaces
+/// (e.g. forskrivning for <Legemiddelpakning>).
+pub(crate) fn catalog<'a>(document: &'a Document, name: &str) -> Option<Node<'a, 'a>> {
+    let root = document.root_element();
+    let namespace = root.tag_name().namespace();
+
+    root.children()
+        .find(|n| n.tag_name().name() == name && n.tag_name().namespace() == namespace)
 }
 
-/// Extract a single value from a node
-pub(crate) fn string_value(node: &Node, tag: &str) -> String {
-    node.children()
-        .find(|n| n.has_tag_name(tag))
-        .and_then(|n| n.text())
-        .unwrap_or("")
-        .to_string()
+/// Extract a single value from a node. Returns None if the element
+/// is missing or has no text.
+pub(crate) fn string_value(node: &Node, tag: &str) -> Option<St> {
+    kith::st(node, tag).filter(|v| !v.trim().is_empty())
 }
 
 /// Extracts the <HentetDato></HentetDato> from the xml file
@@ -51,28 +46,29 @@ pub(crate) fn string_value(node: &Node, tag: &str) -> String {
 /// let fest = Fest::new("test_fest.xml").unwrap();
 /// let date = fest.delivery_date();
 ///
-/// assert_eq!("2024-09-09T14:21:28", date.date());
+/// assert_eq!(date.date(), Some("2024-09-09T14:21:28"));
 /// ```
-pub(crate) fn delivery_date(content: &String) -> String {
-    string_value(&document(content).root_element(), "HentetDato")
+pub(crate) fn delivery_date(document: &Document) -> Option<String> {
+    string_value(&document.root_element(), "HentetDato")
 }
 
 /// Retreives the Metadata from xml string
 /// Its the <Enkeltoppforing> that contains unique id,
-/// time of creation and status
-pub(crate) fn metadata(node: &Node) -> (String, String) {
-    let id = string_value(&node, "Id");
-    let time = string_value(&node, "Tidspunkt");
+/// time of creation and status. Returns None if the id or time
+/// is missing.
+pub(crate) fn metadata(node: &Node) -> Option<(String, String)> {
+    let id = string_value(node, "Id")?;
+    let time = string_value(node, "Tidspunkt")?;
 
-    (id, time)
+    Some((id, time))
 }
 
 /// Retrieves the xml from <OppfInteraksjon>
 pub(crate) fn interaction(node: &Node) -> Option<Interaction> {
-    let metadata = Metadata::new(node);
-    let node = move_node_forward(&node, "Interaksjon")?;
+    let metadata = Metadata::new(node)?;
+    let node = move_node_forward(node, "Interaksjon")?;
 
-    let id = string_value(&node, "Id");
+    let id = string_value(&node, "Id")?;
     let relevance = Cs::new(&node, "Relevans");
     let consequence = string_value(&node, "KliniskKonsekvens");
     let mechanism = string_value(&node, "Interaksjonsmekanisme");
@@ -84,10 +80,10 @@ pub(crate) fn interaction(node: &Node) -> Option<Interaction> {
         .filter(|x| x.has_tag_name("Substansgruppe"))
         .flat_map(|x| x.children())
         .filter(|s| s.has_tag_name("Substans"))
-        .map(|s| {
-            let name = string_value(&s, "Substans");
+        .filter_map(|s| {
+            let name = string_value(&s, "Substans")?;
             let atc = Cv::new(&s, "Atc");
-            Substance::new(name, atc)
+            Some(Substance::new(name, atc))
         })
         .collect();
 
@@ -105,54 +101,187 @@ pub(crate) fn interaction(node: &Node) -> Option<Interaction> {
 
 /// Retrives the xml data from <OppfLegemiddelpakning>
 pub(crate) fn package(node: &Node) -> Option<Package> {
-    let metadata = Metadata::new(node);
-    let node = move_node_forward(&node, "Legemiddelpakning")?;
+    package_with(node, |info| Pq::new(info, "Mengde"))
+}
+
+/// Retrives the xml data from <OppfLegemiddelpakning>, with the parser
+/// for the amount in the package (Mengde) that differs between versions
+pub(crate) fn package_with<F>(node: &Node, quantity: F) -> Option<Package>
+where
+    F: Fn(&Node) -> Option<Pq>,
+{
+    let metadata = Metadata::new(node)?;
+    let node = move_node_forward(node, "Legemiddelpakning")?;
+    let info = move_node_forward(&node, "Pakningsinfo");
 
     Package::from(
         metadata,
         Cv::new(&node, "Atc"),
-        string_value(&node, "NavnFormStyrke"),
+        string_value(&node, "NavnFormStyrke")?,
         Cs::new(&node, "Reseptgruppe"),
-        string_value(&node, "Id"),
-        string_value(&node, "Varenr"),
+        Cv::new(&node, "LegemiddelformKort"),
+        string_value(&node, "Id")?,
+        string_value(&node, "Varenr")?,
         string_value(&node, "Ean"),
         exchange_group(&node),
+        info.and_then(|i| quantity(&i)),
+        info.and_then(|i| Pq::new(&i, "DDD")),
+        marketing_info(&node),
+        node.children().filter(|n| n.has_tag_name("PrisVare")).filter_map(|n| price(&n)).collect(),
     )
 }
 
-/// Retrieves all the packages (OppfLegemiddelpakning) from the xml file
-pub(crate) fn packages(document: &Document) -> Vec<Package> {
-    document
-        .root_element()
-        .children()
-        .find(|n| n.has_tag_name("KatLegemiddelpakning"))
+/// Retrieves the <Markedsforingsinfo> from <Legemiddelpakning>
+pub(crate) fn marketing_info(node: &Node) -> Option<MarketingInfo> {
+    let node = move_node_forward(node, "Markedsforingsinfo")?;
+    let date = |tag| kith::st(&node, tag).and_then(|d| Timestamp::parse(&d));
+
+    Some(MarketingInfo::from(
+        date("Markedsforingsdato"),
+        date("AvregDato"),
+        date("MidlUtgattDato"),
+    ))
+}
+
+/// Retrieves a <PrisVare> from <Legemiddelpakning>. Prices without a
+/// type or amount are skipped.
+pub(crate) fn price(node: &Node) -> Option<Price> {
+    let date = |tag| kith::st(node, tag).and_then(|d| Timestamp::parse(&d));
+
+    Some(Price::from(
+        Cv::new(node, "Type")?,
+        Mo::new(node, "Pris")?,
+        date("GyldigFraDato"),
+        date("GyldigTilDato"),
+    ))
+}
+
+/// Retrieves all the packages (OppfLegemiddelpakning) from the xml file.
+/// The packages that are skipped are added to the report.
+pub(crate) fn packages(document: &Document, report: &mut ParseReport) -> Vec<Package> {
+    packages_with(document, package, report)
+}
+
+/// Retrieves all the packages (OppfLegemiddelpakning) with the parser
+/// for the version
+pub(crate) fn packages_with(
+    document: &Document,
+    package: fn(&Node) -> Option<Package>,
+    report: &mut ParseReport,
+) -> Vec<Package> {
+    parse_entries(entries(document, "KatLegemiddelpakning", "OppfLegemiddelpakning"), package, report)
+}
+
+/// Retreives all the interactions (OppfInteraksjon) from the xml file
+pub(crate) fn interactions(document: &Document, report: &mut ParseReport) -> Vec<Interaction> {
+    parse_entries(entries(document, "KatInteraksjon", "OppfInteraksjon"), interaction, report)
+}
+
+/// Retrieves the xml data from <OppfVirkestoff> that contains
+/// a <VirkestoffMedStyrke>. Substances without strength are skipped.
+pub(crate) fn substance_strength(node: &Node) -> Option<SubstanceStrength> {
+    let metadata = Metadata::new(node)?;
+    let node = move_node_forward(node, "VirkestoffMedStyrke")?;
+
+    Some(SubstanceStrength::from(
+        metadata,
+        string_value(&node, "Id")?,
+        Rto::new(&node, "Styrke", "StyrkeNevner")?,
+        string_value(&node, "RefVirkestoff")?,
+        Cv::new(&node, "AtcKombipreparat"),
+    ))
+}
+
+/// Retrieves the xml data from <OppfVirkestoff> that contains a
+/// <Virkestoff>. Substances without a name are skipped.
+pub(crate) fn active_substance(node: &Node) -> Option<ActiveSubstance> {
+    let metadata = Metadata::new(node)?;
+    let node = move_node_forward(node, "Virkestoff")?;
+    let name = string_value(&node, "Navn")?;
+
+    Some(ActiveSubstance::from(metadata, string_value(&node, "Id")?, name))
+}
+
+/// Checks if the <OppfVirkestoff> is an active substance, with a
+/// <Virkestoff>, and not a substance with strength
+pub(crate) fn is_active_substance(node: &Node) -> bool {
+    move_node_forward(node, "Virkestoff").is_some()
+}
+
+/// Retrieves all the active substances (OppfVirkestoff with a
+/// <Virkestoff>) from the xml file
+pub(crate) fn active_substances(document: &Document, report: &mut ParseReport) -> Vec<ActiveSubstance> {
+    let mut nodes = entries(document, "KatVirkestoff", "OppfVirkestoff");
+    nodes.retain(is_active_substance);
+
+    parse_entries(nodes, active_substance, report)
+}
+
+/// Retrieves all the substances with strength (OppfVirkestoff) from the
+/// xml file. The entries that aren't an active substance are read as
+/// substances with strength.
+pub(crate) fn substance_strengths(document: &Document, report: &mut ParseReport) -> Vec<SubstanceStrength> {
+    let mut nodes = entries(document, "KatVirkestoff", "OppfVirkestoff");
+    nodes.retain(|n| !is_active_substance(n));
+
+    parse_entries(nodes, substance_strength, report)
+}
+
+/// Counts the entries (Oppf*) in the catalog, also the entries that
+/// can't be parsed
+pub(crate) fn entry_count(document: &Document, catalog: Catalog) -> usize {
+    self::catalog(document, catalog.name())
         .into_iter()
         .flat_map(|n| n.children())
-        .filter(|x| x.has_tag_name("OppfLegemiddelpakning"))
-        .filter_map(|x| package(&x))
-        .collect()
+        .filter(|x| x.has_tag_name(catalog.entry()))
+        .count()
 }
 
-/// Retreives all the interactions (OppfInteraksjon) from the xml file
-pub(crate) fn interactions(document: &Document) -> Vec<Interaction> {
-    document
-        .root_element()
-        .children()
-        .find(|n| n.has_tag_name("KatInteraksjon"))
+// the entries (Oppf*) in the catalog (Kat*)
+fn entries<'a>(document: &'a Document, catalog_name: &str, entry: &str) -> Vec<Node<'a, 'a>> {
+    catalog(document, catalog_name)
         .into_iter()
         .flat_map(|n| n.children())
-        .filter(|x| x.has_tag_name("OppfInteraksjon"))
-        .filter_map(|x| interaction(&x))
+        .filter(|x| x.has_tag_name(entry))
         .collect()
 }
 
+// parses the entries in order. The entries the parser can't read are
+// skipped, with a warning in the report.
+fn parse_entries<T: Send>(nodes: Vec<Node>, parse: fn(&Node) -> Option<T>, report: &mut ParseReport) -> Vec<T> {
+    let parsed = parse_all(&nodes, parse);
+
+    nodes
+        .iter()
+        .zip(parsed)
+        .filter_map(|(node, entry)| {
+            if entry.is_none() {
+                report.skipped(node);
+            }
+            entry
+        })
+        .collect()
+}
+
+// across threads with the parallel feature
+#[cfg(feature = "parallel")]
+fn parse_all<T: Send>(nodes: &[Node], parse: fn(&Node) -> Option<T>) -> Vec<Option<T>> {
+    use rayon::prelude::*;
+
+    nodes.par_iter().map(parse).collect()
+}
+
+#[cfg(not(feature = "parallel"))]
+fn parse_all<T>(nodes: &[Node], parse: fn(&Node) -> Option<T>) -> Vec<Option<T>> {
+    nodes.iter().map(parse).collect()
+}
+
 /// Retrieves the Exchange group. <PakningByttegruppe>
 pub(crate) fn exchange_group(node: &Node) -> Option<ExchangeGroup> {
-    node.children()
-        .find(|n| n.has_tag_name("PakningByttegruppe"))
-        .map(|n| string_value(&n, "RefByttegruppe"))
-        .filter(|id| !id.is_empty())
-        .and_then(|id| ExchangeGroup::from(id, None, None))
+    let node = move_node_forward(node, "PakningByttegruppe")?;
+    let id = string_value(&node, "RefByttegruppe")?;
+
+    ExchangeGroup::from(id, kith::st(&node, "GyldigFraDato"), kith::st(&node, "GyldigTilDato"))
 }
 
 // moves the xml node forward
@@ -160,6 +289,28 @@ fn move_node_forward<'a>(node: &'a Node, destination: &str) -> Option<Node<'a, '
     node.children().find(|n| n.has_tag_name(destination))
 }
 
+/// The packages of test_fest.xml, for the tests
+#[cfg(test)]
+pub(crate) fn test_packages() -> Vec<Package> {
+    test_catalogs().0
+}
+
+/// The packages, interactions, active substances and substances with
+/// strength of test_fest.xml, for the tests
+#[cfg(test)]
+pub(crate) fn test_catalogs() -> (Vec<Package>, Vec<Interaction>, Vec<ActiveSubstance>, Vec<SubstanceStrength>) {
+    let content = std::fs::read_to_string("test_fest.xml").unwrap();
+    let document = document(&content).unwrap();
+    let report = &mut ParseReport::default();
+
+    (
+        packages(&document, report),
+        interactions(&document, report),
+        active_substances(&document, report),
+        substance_strengths(&document, report),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -181,24 +332,51 @@ mod tests {
     #[test]
     fn test_document() {
         let content = file_content();
-        let document = document(&content);
-        assert_eq!(document.root_element().has_tag_name("FEST"), true);
+        let document = document(&content).unwrap();
+        assert!(document.root_element().has_tag_name("FEST"));
+    }
+
+    #[test]
+    fn test_document_invalid() {
+        assert!(matches!(document("<FEST><KatLegemiddelpakning>"), Err(FestError::Xml { .. })));
+    }
+
+    #[test]
+    fn test_check() {
+        let content = file_content();
+        let document = document(&content).unwrap();
+        assert!(check(&document, &["KatLegemiddelpakning", "KatInteraksjon"]).is_ok());
+
+        let result = check(&document, &["KatHandelsvare"]);
+        assert!(matches!(result, Err(FestError::MissingCatalog(c)) if c == "KatHandelsvare"));
+    }
+
+    #[test]
+    fn test_catalog_namespace() {
+        let content = r#"<FEST xmlns="http://www.kith.no/xmlstds/eresept/m30/2014-12-01">
+            <KatInteraksjon/>
+            <KatLegemiddelpakning xmlns="urn:other"/>
+        </FEST>"#;
+        let document = document(content).unwrap();
+
+        assert!(catalog(&document, "KatInteraksjon").is_some());
+        assert!(catalog(&document, "KatLegemiddelpakning").is_none());
     }
 
     #[test]
     fn test_delivery_date() {
         let content = file_content();
-        let date = delivery_date(&content);
-        assert_eq!(date, "2024-09-09T14:21:28");
+        let date = delivery_date(&document(&content).unwrap());
+        assert_eq!(date.as_deref(), Some("2024-09-09T14:21:28"));
     }
 
     #[test]
     fn test_metadata() {
         let content = file_content();
-        let document = document(&content);
+        let document = document(&content).unwrap();
 
         if let Some(node) = find_first_package_node(&document) {
-            let (res1, res2) = metadata(&node);
+            let (res1, res2) = metadata(&node).unwrap();
             assert_eq!(res1, "ID_F994748F-3A21-4FC3-9964-DBE097924A75");
             assert_eq!(res2, "2024-04-21T00:51:31");
         } else {
@@ -209,11 +387,11 @@ mod tests {
     #[test]
     fn test_cs() {
         let content = file_content();
-        let document = document(&content);
+        let document = document(&content).unwrap();
 
         if let Some(node) = find_first_package_node(&document) {
-            let (res1, _) = cs(&node, "Status");
-            assert_eq!(res1, "A");
+            let cs = Cs::new(&node, "Status").unwrap();
+            assert_eq!(cs.v(), Some("A"));
         } else {
             panic!("Could not find package node");
         }
@@ -222,16 +400,16 @@ mod tests {
     #[test]
     fn test_cv() {
         let content = file_content();
-        let document = document(&content);
+        let document = document(&content).unwrap();
 
         if let Some(node) = find_first_package_node(&document) {
             // Navigate to the Legemiddelpakning child
             for child in node.children() {
                 if child.has_tag_name("Legemiddelpakning") {
-                    let (res1, res2, res3) = cv(&child, "LegemiddelformKort");
-                    assert_eq!(res1, "32");
-                    assert_eq!(res2, "2.16.578.1.12.4.1.1.7448");
-                    assert_eq!(res3, "Kapsel");
+                    let cv = Cv::new(&child, "LegemiddelformKort").unwrap();
+                    assert_eq!(cv.v(), Some("32"));
+                    assert_eq!(cv.s(), Some("2.16.578.1.12.4.1.1.7448"));
+                    assert_eq!(cv.dn(), Some("Kapsel"));
                     return;
                 }
             }
@@ -244,11 +422,12 @@ mod tests {
     #[test]
     fn test_string_value() {
         let content = file_content();
-        let document = document(&content);
+        let document = document(&content).unwrap();
 
         if let Some(node) = find_first_package_node(&document) {
             let id = string_value(&node, "Id");
-            assert_eq!(id, "ID_F994748F-3A21-4FC3-9964-DBE097924A75");
+            assert_eq!(id.as_deref(), Some("ID_F994748F-3A21-4FC3-9964-DBE097924A75"));
+            assert_eq!(string_value(&node, "Ukjent"), None);
         } else {
             panic!("Could not find package node");
         }
@@ -257,7 +436,7 @@ mod tests {
     #[test]
     fn test_package() {
         let content = file_content();
-        let document = document(&content);
+        let document = document(&content).unwrap();
 
         if let Some(node) = find_first_package_node(&document) {
             let package = package(&node);
@@ -272,16 +451,42 @@ mod tests {
     #[test]
     fn test_packages() {
         let content = file_content();
-        let document = document(&content);
+        let document = document(&content).unwrap();
 
-        let packages = packages(&document);
+        let packages = packages(&document, &mut Default::default());
         assert_eq!(packages.len(), 5);
     }
 
+    #[test]
+    fn test_pq() {
+        let content = file_content();
+        let document = document(&content).unwrap();
+
+        let packages = packages(&document, &mut Default::default());
+        let quantity = packages[0].quantity().unwrap();
+        assert_eq!(quantity.v(), Some(28.0));
+        assert_eq!(quantity.u(), Some("stk"));
+        assert!(packages[2].quantity().is_none());
+    }
+
+    #[test]
+    fn test_substance_strengths() {
+        let content = file_content();
+        let document = document(&content).unwrap();
+
+        // the <Virkestoff> without strength is skipped
+        let strengths = substance_strengths(&document, &mut Default::default());
+        assert_eq!(strengths.len(), 2);
+        assert_eq!(strengths[0].strength().to_string(), "10 mg");
+        assert_eq!(strengths[1].strength().to_string(), "5 mg/ml");
+        assert_eq!(strengths[1].atc().unwrap().v(), Some("B01AA03"));
+        assert!(strengths[0].atc().is_none());
+    }
+
 //    #[test]
 //    fn test_interactions() {
 //        let content = file_content();
-//        let document = document(&content);
+//        let document = document(&content).unwrap();
 //
 //        let interactions = interactions(&document);
 //        assert_eq!(interactions.len(), 9793);
@@ -290,7 +495,7 @@ mod tests {
 //    #[test]
 //    fn test_interaction() {
 //        let content = file_content();
-//        let document = document(&content);
+//        let document = document(&content).unwrap();
 //
 //        let node = document.get_node(NodeId::new(3658322)).unwrap();
 //        let interaction = interaction(&node);
@@ -314,4 +519,17 @@ mod tests {
 
    //     assert_eq!(group.is_some(), true);
    // }
+
+    #[test]
+    fn test_parse_entries_order() {
+        let content = file_content();
+        let document = document(&content).unwrap();
+
+        let nodes = entries(&document, "KatLegemiddelpakning", "OppfLegemiddelpakning");
+        let sequential: Vec<Package> = nodes.iter().filter_map(package).collect();
+        let parsed = parse_entries(nodes, package, &mut Default::default());
+
+        let itemnums = |p: &[Package]| p.iter().map(|p| p.itemnum().to_string()).collect::<Vec<_>>();
+        assert_eq!(itemnums(&parsed), itemnums(&sequential));
+    }
 }
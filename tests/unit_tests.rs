@@ -3,7 +3,7 @@ use std::{env, path::PathBuf};
 #[cfg(test)]
 mod tests {
     use super::*;
-    use festlib::Fest;
+    use festlib::{EntryRef, Fest};
 
     // Helper function to get the test file path relative to cargo project root
     fn get_test_file_path() -> String {
@@ -21,12 +21,36 @@ mod tests {
         assert!(result.is_ok(), "Should be able to create Fest from valid XML file");
     }
 
+    #[test]
+    fn test_fest_creation_from_zip() {
+        let manifest_dir = env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR not set");
+        let test_file = PathBuf::from(manifest_dir).join("test_fest.zip");
+
+        let fest = Fest::new(&test_file).expect("Should detect the zip archive");
+        assert_eq!(fest.packages().len(), 5);
+
+        let fest = Fest::from_zip(&test_file).expect("Should read the zip archive");
+        assert_eq!(fest.delivery_date().date(), Some("2024-09-09T14:21:28"));
+
+        assert!(Fest::from_zip(get_test_file_path()).is_err());
+    }
+
+    #[test]
+    fn test_fest_from_reader() {
+        let content = std::fs::read(get_test_file_path()).unwrap();
+        let fest = Fest::from_reader(std::io::Cursor::new(content)).expect("Should read from memory");
+        assert_eq!(fest.packages().len(), 5);
+
+        let fest: Fest = include_str!("../test_fest.xml").parse().expect("Should parse the string");
+        assert_eq!(fest.delivery_date().date(), Some("2024-09-09T14:21:28"));
+    }
+
     #[test]
     fn test_fest_delivery_date() {
         let test_file = get_test_file_path();
         if let Ok(fest) = Fest::new(&test_file) {
             let date = fest.delivery_date();
-            assert_eq!(date.date(), "2024-09-09T14:21:28");
+            assert_eq!(date.date(), Some("2024-09-09T14:21:28"));
         } else {
             panic!("Failed to create Fest instance");
         }
@@ -43,7 +67,7 @@ mod tests {
             let package = &packages[0];
             assert_eq!(package.id(), "ID_0138BA04-7B67-4FB5-B44D-7491336CAF20");
             assert_eq!(package.itemnum(), "061561");
-            assert_eq!(package.ean(), "7001234567890");
+            assert_eq!(package.ean().unwrap(), "7001234567890");
         } else {
             panic!("Failed to create Fest instance");
         }
@@ -53,12 +77,12 @@ mod tests {
     fn test_find_package_by_itemnum() {
         let test_file = get_test_file_path();
         if let Ok(fest) = Fest::new(&test_file) {
-            let package = fest.find_package("061561");
+            let package = fest.find_package("061561").unwrap();
             assert!(package.is_some(), "Should find package with itemnum 061561");
             
             if let Some(p) = package {
                 assert_eq!(p.id(), "ID_0138BA04-7B67-4FB5-B44D-7491336CAF20");
-                assert_eq!(p.ean(), "7001234567890");
+                assert_eq!(p.ean().unwrap(), "7001234567890");
             }
         } else {
             panic!("Failed to create Fest instance");
@@ -69,7 +93,7 @@ mod tests {
     fn test_find_nonexistent_package() {
         let test_file = get_test_file_path();
         if let Ok(fest) = Fest::new(&test_file) {
-            let package = fest.find_package("999999");
+            let package = fest.find_package("999999").unwrap();
             assert!(package.is_none(), "Should not find package with non-existent itemnum");
         } else {
             panic!("Failed to create Fest instance");
@@ -87,10 +111,9 @@ mod tests {
     fn test_find_generic() {
         let test_file = get_test_file_path();
         if let Ok(fest) = Fest::new(&test_file) {
-            if let Some(package) = fest.find_package("061561") {
-                let generics = fest.find_generic(&package);
-                assert!(generics.is_some());
-                assert_eq!(generics.unwrap().len(), 4); // Should find 4 generics with same exchange group
+            if let Some(package) = fest.find_package("061561").unwrap() {
+                let generics = fest.find_generic(package).unwrap();
+                assert_eq!(generics.count(), 4); // Should find 4 generics with same exchange group
             } else {
                 panic!("Could not find package for generic test");
             }
@@ -103,20 +126,108 @@ mod tests {
     fn test_interactions() {
         let test_file = get_test_file_path();
         if let Ok(fest) = Fest::new(&test_file) {
-            let package1 = fest.find_package("061561");
-            let package2 = fest.find_package("017701");
+            let package1 = fest.find_package("061561").unwrap();
+            let package2 = fest.find_package("017701").unwrap();
             
             assert!(package1.is_some());
             assert!(package2.is_some());
             
-            let packages = vec![package1.unwrap(), package2.unwrap()];
-            let interactions = fest.find_interaction(&packages);
+            let packages = [package1.unwrap(), package2.unwrap()];
+            let interactions = fest.find_interaction(&packages).unwrap();
             
             // Our test XML has 1 interaction between these ATCs
-            assert!(interactions.is_some());
-            assert_eq!(interactions.unwrap().len(), 1);
+            assert_eq!(interactions.count(), 1);
         } else {
             panic!("Failed to create Fest instance");
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_no_results() {
+        let test_file = get_test_file_path();
+        let fest = Fest::new(&test_file).expect("Failed to create Fest instance");
+
+        // no exchange group
+        let package = fest.find_package("017701").unwrap().unwrap();
+        assert_eq!(fest.find_generic(package).unwrap().count(), 0);
+
+        // a single package, or none
+        assert_eq!(fest.find_interaction(&[package]).unwrap().count(), 0);
+        assert_eq!(fest.find_interaction(&[]).unwrap().count(), 0);
+
+        assert_eq!(fest.find_packages_by_atc("X99XX99").unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let test_file = get_test_file_path();
+        let fest = Fest::new(&test_file).expect("Failed to create Fest instance");
+
+        let json = serde_json::to_string(&fest).expect("Should serialize Fest");
+        let restored: Fest = serde_json::from_str(&json).expect("Should deserialize Fest");
+
+        assert_eq!(restored.delivery_date().date(), Some("2024-09-09T14:21:28"));
+        assert_eq!(restored.packages().len(), 5);
+        assert_eq!(restored.substance_strengths().len(), 2);
+
+        let package = restored.find_package("061561").unwrap().expect("Should find package after round trip");
+        assert_eq!(package.ean().unwrap(), "7001234567890");
+        assert_eq!(package.quantity().unwrap().to_string(), "28 stk");
+
+        let generics = restored.find_generic(package).unwrap();
+        assert_eq!(generics.count(), 4);
+
+        let package2 = restored.find_package("017701").unwrap().unwrap();
+        let interactions = restored.find_interaction(&[package, package2]).unwrap();
+        assert_eq!(interactions.count(), 1);
+
+        // serializing the restored fest should give the same data
+        assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+    }
+
+    #[test]
+    fn test_validate() {
+        let test_file = get_test_file_path();
+
+        let violations = Fest::validate(&test_file).expect("Should validate the fest file");
+        assert!(violations.is_empty(), "{:?}", violations);
+
+        assert!(Fest::validate("does_not_exist.xml").is_err());
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let test_file = get_test_file_path();
+        let fest = Fest::new(&test_file).expect("Failed to create Fest instance");
+
+        let path = std::env::temp_dir().join(format!("festlib_test_{}.snapshot", std::process::id()));
+        fest.save_snapshot(&path).expect("Should save the snapshot");
+
+        let snapshot = Fest::load_snapshot(&path).expect("Should load the snapshot");
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(snapshot.delivery_date().date(), fest.delivery_date().date());
+        assert_eq!(snapshot.content_hash(), fest.content_hash());
+        assert_eq!(serde_json::to_string(&snapshot).unwrap(), serde_json::to_string(&fest).unwrap());
+    }
+
+    #[test]
+    fn test_find_package_by_identifiers() {
+        let test_file = get_test_file_path();
+        let fest = Fest::new(&test_file).expect("Failed to create Fest instance");
+
+        let package = fest.find_package_by_ean("7001234567892").unwrap().expect("Should find package by EAN");
+        assert_eq!(package.itemnum(), "017701");
+        assert_eq!(fest.find_package_by_id(package.id()).unwrap().unwrap().itemnum(), "017701");
+        assert_eq!(fest.find_package_any(package.metadata().id()).unwrap().unwrap().itemnum(), "017701");
+
+        assert!(fest.find_package_by_ean("7001234567899").unwrap().is_none());
+        assert!(fest.find_package_any("ID_INT001-C908-43D8-AA07-9F8F00E6E7A3").unwrap().is_none());
+        assert!(fest.find_entry_by_oppf_id("ID_INT001-C908-43D8-AA07-9F8F00E6E7A3").is_some());
+
+        match fest.find_entry_by_oppf_id("ID_VIRK001-4FC3-9964-DBE097924A75") {
+            Some(EntryRef::ActiveSubstance(substance)) => assert_eq!(substance.name(), "Test Substance A"),
+            other => panic!("Expected an active substance, got {:?}", other),
+        }
+    }
+}